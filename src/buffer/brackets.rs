@@ -0,0 +1,65 @@
+use ropey::RopeSlice;
+
+const PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+pub fn is_open(c: char) -> bool {
+    PAIRS.iter().any(|(open, _)| *open == c)
+}
+
+pub fn is_close(c: char) -> bool {
+    PAIRS.iter().any(|(_, close)| *close == c)
+}
+
+pub fn is_bracket(c: char) -> bool {
+    is_open(c) || is_close(c)
+}
+
+/// Index of the bracket that pairs with the one at `index`. Brackets where
+/// `is_code` is false (strings, comments) don't count.
+pub fn matching(rope: &RopeSlice, index: usize, is_code: &dyn Fn(usize) -> bool) -> Option<usize> {
+    let c = rope.char(index);
+    let mut depth = 0;
+    if let Some((open, close)) = PAIRS.iter().find(|(open, _)| *open == c) {
+        for (offset, c) in rope.chars_at(index + 1).enumerate() {
+            let i = index + 1 + offset;
+            if (c == *open || c == *close) && is_code(i) {
+                if c == *open {
+                    depth += 1;
+                } else if depth == 0 {
+                    return Some(i);
+                } else {
+                    depth -= 1;
+                }
+            }
+        }
+    } else if let Some((open, close)) = PAIRS.iter().find(|(_, close)| *close == c) {
+        let mut chars = rope.chars_at(index);
+        let mut i = index;
+        while let Some(c) = chars.prev() {
+            i -= 1;
+            if (c == *open || c == *close) && is_code(i) {
+                if c == *close {
+                    depth += 1;
+                } else if depth == 0 {
+                    return Some(i);
+                } else {
+                    depth -= 1;
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Like vim's `%`, when the cursor isn't on a bracket the next one on the line is used
+pub fn find_match(
+    rope: &RopeSlice,
+    index: usize,
+    is_code: &dyn Fn(usize) -> bool,
+) -> Option<usize> {
+    let line = rope.char_to_line(index);
+    let line_end = rope.line_to_char(line) + rope.line(line).len_chars();
+    (index..line_end)
+        .find(|i| is_bracket(rope.char(*i)) && is_code(*i))
+        .and_then(|i| matching(rope, i, is_code))
+}
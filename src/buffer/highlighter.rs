@@ -1,13 +1,40 @@
-use syntect::{parsing::SyntaxReference, highlighting::Style};
 use ropey::RopeSlice;
+use syntect::{
+    highlighting::{HighlightIterator, HighlightState, Highlighter, Style},
+    parsing::{ParseState, Scope, ScopeStack, SyntaxReference},
+};
 
+use super::brackets::{is_close, is_open};
 use crate::state::Config;
 
 use std::ops::Range;
 
+const RAINBOW: &[[f32; 4]] = &[
+    [1., 0.85, 0.3, 1.],
+    [0.85, 0.45, 0.9, 1.],
+    [0.35, 0.7, 1., 1.],
+    [0.5, 0.9, 0.5, 1.],
+    [1., 0.55, 0.35, 1.],
+    [0.4, 0.9, 0.9, 1.],
+];
+
+fn to_color(style: &Style) -> [f32; 4] {
+    let syntect::highlighting::Color { r, g, b, a } = style.foreground;
+    [
+        r as f32 / 255.,
+        g as f32 / 255.,
+        b as f32 / 255.,
+        a as f32 / 255.,
+    ]
+}
+
 pub struct HighlightContainer {
     syntax: SyntaxReference,
     lines: Vec<Vec<(Style, Range<usize>)>>,
+    /// Byte ranges of each line that are inside strings or comments
+    ignored: Vec<Vec<Range<usize>>>,
+    /// Byte offset and nesting depth of each bracket outside strings and comments
+    brackets: Vec<Vec<(usize, usize)>>,
 }
 
 impl HighlightContainer {
@@ -15,21 +42,50 @@ impl HighlightContainer {
         HighlightContainer {
             syntax,
             lines: Vec::new(),
+            ignored: Vec::new(),
+            brackets: Vec::new(),
         }
     }
 
+    /// False for anything in a string or comment
+    pub fn is_code(&self, line: usize, byte: usize) -> bool {
+        self.ignored
+            .get(line)
+            .map(|ranges| !ranges.iter().any(|range| range.contains(&byte)))
+            .unwrap_or(true)
+    }
+
+    pub fn syntax_name(&self) -> &str {
+        &self.syntax.name
+    }
+
     /// Highlights whole file
     pub fn highlight(&mut self, text: &RopeSlice, config: &Config) {
         self.lines.clear();
-        use syntect::easy::HighlightLines;
-        let mut h = HighlightLines::new(&self.syntax, &config.theme);
+        self.ignored.clear();
+        self.brackets.clear();
+        let highlighter = Highlighter::new(&config.theme);
+        let mut parse_state = ParseState::new(&self.syntax);
+        let mut highlight_state = HighlightState::new(&highlighter, ScopeStack::new());
+        let mut scopes = ScopeStack::new();
+        let ignored_scopes = [
+            Scope::new("string").expect("valid scope"),
+            Scope::new("comment").expect("valid scope"),
+        ];
+        let is_ignored = |scopes: &ScopeStack| {
+            scopes.as_slice().iter().any(|scope| {
+                ignored_scopes
+                    .iter()
+                    .any(|ignored| ignored.is_prefix_of(*scope))
+            })
+        };
+        let mut depth = 0;
         for line_slice in text.lines() {
             let line: std::borrow::Cow<str> = line_slice.into();
-            let chunks = h.highlight(&line, &config.syntax_set);
+            let ops = parse_state.parse_line(&line, &config.syntax_set);
             let mut cursor = 0;
             self.lines.push(
-                chunks
-                    .into_iter()
+                HighlightIterator::new(&mut highlight_state, &ops, &line, &highlighter)
                     .map(|(style, val)| {
                         // TODO: use an iter directly instead of a temp vec
                         let rng = cursor..cursor + val.len();
@@ -37,31 +93,69 @@ impl HighlightContainer {
                         (style, rng)
                     })
                     .collect(),
-            )
+            );
+
+            let mut ignored = Vec::new();
+            let mut start = 0;
+            for (position, op) in ops.iter() {
+                if *position > start {
+                    if is_ignored(&scopes) {
+                        ignored.push(start..*position);
+                    }
+                    start = *position;
+                }
+                scopes.apply(op);
+            }
+            if start < line.len() && is_ignored(&scopes) {
+                ignored.push(start..line.len());
+            }
+
+            let mut brackets = Vec::new();
+            for (byte, c) in line.char_indices() {
+                if ignored.iter().any(|range| range.contains(&byte)) {
+                    continue;
+                }
+                if is_open(c) {
+                    brackets.push((byte, depth));
+                    depth += 1;
+                } else if is_close(c) {
+                    depth = depth.saturating_sub(1);
+                    brackets.push((byte, depth));
+                }
+            }
+            self.ignored.push(ignored);
+            self.brackets.push(brackets);
         }
     }
 
-    /// Renders what is already highlighted
-    pub fn render(&self, text: &RopeSlice, ui: &imgui::Ui) {
-        for (chunks, line_slice) in self.lines.iter().zip(text.lines()) {
+    /// Renders what is already highlighted. With `rainbow` brackets are colored by how deep they are.
+    pub fn render(&self, text: &RopeSlice, ui: &imgui::Ui, rainbow: bool) {
+        let draw = |color: [f32; 4], val: &str| {
+            if val.is_empty() {
+                return;
+            }
+            ui.text_colored(color, val);
+            ui.same_line(0.);
+            let [cursor_x, cursor_y] = ui.cursor_pos();
+            ui.set_cursor_pos([cursor_x - 0.25, cursor_y]); // HACK: I can't figure out how to stop the stupid spacing
+        };
+        for (index, (chunks, line_slice)) in self.lines.iter().zip(text.lines()).enumerate() {
             let line: std::borrow::Cow<str> = line_slice.into();
             if ui.is_cursor_rect_visible([10., 10.]) {
+                let brackets = match self.brackets.get(index) {
+                    Some(brackets) if rainbow => brackets.as_slice(),
+                    _ => &[],
+                };
                 ui.group(|| {
                     for (style, rng) in chunks {
-                        let val = &line[rng.clone()];
-                        let syntect::highlighting::Color { r, g, b, a } = style.foreground;
-                        ui.text_colored(
-                            [
-                                r as f32 / 255.,
-                                g as f32 / 255.,
-                                b as f32 / 255.,
-                                a as f32 / 255.,
-                            ],
-                            val,
-                        );
-                        ui.same_line(0.);
-                        let [cursor_x, cursor_y] = ui.cursor_pos();
-                        ui.set_cursor_pos([cursor_x - 0.25, cursor_y]); // HACK: I can't figure out how to stop the stupid spacing
+                        let mut start = rng.start;
+                        for (byte, depth) in brackets.iter().filter(|(byte, _)| rng.contains(byte))
+                        {
+                            draw(to_color(style), &line[start..*byte]);
+                            draw(RAINBOW[depth % RAINBOW.len()], &line[*byte..*byte + 1]);
+                            start = byte + 1;
+                        }
+                        draw(to_color(style), &line[start..rng.end]);
                     }
                 });
             } else {
@@ -0,0 +1,99 @@
+use ropey::RopeSlice;
+
+use std::{collections::HashMap, ops::Range};
+
+/// Oldest changes get dropped past this, same as vim
+const MAX_CHANGES: usize = 100;
+
+/// Positions in a buffer that move along with the text around them.
+/// Everything is a char index so edits only have to shift numbers.
+#[derive(Debug, Clone, Default)]
+pub struct Marks {
+    named: HashMap<char, usize>,
+    /// Where edits happened, oldest first, one per line in a row
+    changes: Vec<usize>,
+    /// Where `g;` and `g,` are in `changes`, `changes.len()` when not walking it
+    change_index: usize,
+}
+
+impl Marks {
+    pub fn set(&mut self, name: char, index: usize) {
+        self.named.insert(name, index);
+    }
+
+    pub fn remove(&mut self, name: char) {
+        self.named.remove(&name);
+    }
+
+    /// Named ones, not `.`
+    pub fn named(&self) -> impl Iterator<Item = (char, usize)> + '_ {
+        self.named.iter().map(|(name, index)| (*name, *index))
+    }
+
+    /// `.` is the last change
+    pub fn get(&self, name: char) -> Option<usize> {
+        match name {
+            '.' => self.changes.last().copied(),
+            name => self.named.get(&name).copied(),
+        }
+    }
+
+    /// `len` chars went in at `index`
+    pub fn inserted(&mut self, index: usize, len: usize, rope: &RopeSlice) {
+        for mark in self.positions() {
+            if *mark >= index {
+                *mark += len;
+            }
+        }
+        self.record_change(index, rope);
+    }
+
+    /// Marks inside `range` end up where it started
+    pub fn removed(&mut self, range: Range<usize>, rope: &RopeSlice) {
+        let len = range.end - range.start;
+        for mark in self.positions() {
+            if *mark >= range.end {
+                *mark -= len;
+            } else if *mark > range.start {
+                *mark = range.start;
+            }
+        }
+        self.record_change(range.start, rope);
+    }
+
+    /// The change before the last one `g;` went to
+    pub fn older_change(&mut self, count: usize) -> Option<usize> {
+        if self.change_index == 0 {
+            return None;
+        }
+        self.change_index = self.change_index.saturating_sub(count);
+        self.changes.get(self.change_index).copied()
+    }
+
+    pub fn newer_change(&mut self, count: usize) -> Option<usize> {
+        if self.change_index + 1 >= self.changes.len() {
+            return None;
+        }
+        self.change_index = (self.change_index + count).min(self.changes.len() - 1);
+        self.changes.get(self.change_index).copied()
+    }
+
+    fn positions(&mut self) -> impl Iterator<Item = &mut usize> {
+        self.named.values_mut().chain(self.changes.iter_mut())
+    }
+
+    /// `rope` is the text after the edit
+    fn record_change(&mut self, index: usize, rope: &RopeSlice) {
+        let index = index.min(rope.len_chars());
+        let line = rope.char_to_line(index);
+        // Typing a word shouldn't fill the whole list
+        match self.changes.last_mut() {
+            Some(last) if rope.char_to_line((*last).min(rope.len_chars())) == line => *last = index,
+            _ => self.changes.push(index),
+        }
+        if self.changes.len() > MAX_CHANGES {
+            self.changes.remove(0);
+        }
+        self.change_index = self.changes.len();
+    }
+}
@@ -1,39 +1,71 @@
 use crate::{
-    cursor::Cursor,
+    cursor::{outline_char, Cursor},
     error::Error,
     msg::{DeleteDirection, Direction, JumpType},
+    point::Point,
     state::Config,
 };
-use std::borrow::Cow;
+use std::{borrow::Cow, ops::Range, path::Path, time::SystemTime};
 
 use anyhow::Result;
-use ropey::Rope;
+use ropey::{Rope, RopeSlice};
 use slotmap::DefaultKey;
 
-
-
+mod brackets;
 mod highlighter;
-use highlighter::HighlightContainer;
+mod marks;
+mod undo;
+pub use highlighter::HighlightContainer;
+use marks::Marks;
+use undo::{Edit, UndoTree};
+
+const MATCHING_BRACKET_COLOR: [f32; 4] = [1., 1., 1., 0.6];
 
 pub type BufferKey = DefaultKey;
 
 pub struct Buffer {
     rope: Rope,
-    cursor: Cursor,
     file: Option<std::path::PathBuf>,
     highlighter: Option<HighlightContainer>,
+    modified: bool,
+    /// Goes up with every edit
+    version: u64,
+    /// When `file` was last read or written, to notice other programs changing it
+    disk_modified: Option<SystemTime>,
+    /// The change on disk that was last warned about
+    warned_disk_modified: Option<SystemTime>,
+    marks: Marks,
+    undo: UndoTree,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
 }
 
-fn log10(num: usize) -> usize {
-    match num {
-        n if n < 1 => panic!("log10 doesn't work for n < 1"),
-        n if n < 10 => 1,
-        n if n < 100 => 2,
-        n if n < 1000 => 3,
-        n if n < 10000 => 4,
-        n if n < 100_000 => 5,
-        n if n < 1_000_000 => 6,
-        _ => unimplemented!(), // Cross that bridge when we get there
+/// What `count` deletes around `char_index`. Like vim's `x` and `X` it stays on the line
+/// and leaves the line break, unless `join_lines` lets backspace in Insert mode through.
+fn delete_range(
+    rope: &RopeSlice,
+    char_index: usize,
+    direction: DeleteDirection,
+    count: usize,
+    join_lines: bool,
+) -> Range<usize> {
+    let row = rope.char_to_line(char_index);
+    let line = rope.line(row);
+    let line_start = rope.line_to_char(row);
+    let line_break = line
+        .chars()
+        .rev()
+        .take_while(|&c| c == '\n' || c == '\r')
+        .count();
+    let line_end = line_start + line.len_chars() - line_break;
+    match direction {
+        DeleteDirection::Before if join_lines => char_index.saturating_sub(count)..char_index,
+        DeleteDirection::Before => char_index.saturating_sub(count).max(line_start)..char_index,
+        DeleteDirection::After => char_index..(char_index + count).min(line_end),
     }
 }
 
@@ -41,44 +73,59 @@ impl Buffer {
     pub fn new() -> Result<Buffer> {
         Ok(Buffer {
             rope: Rope::new(),
-            cursor: Cursor::new(),
             file: None,
             highlighter: None,
+            modified: false,
+            version: 0,
+            disk_modified: None,
+            warned_disk_modified: None,
+            marks: Marks::default(),
+            undo: UndoTree::default(),
         })
     }
 
     pub fn load_file(file_path: std::path::PathBuf, config: &Config) -> Result<Buffer> {
-        let rope = Rope::from_reader(std::fs::File::open(file_path.as_path())?)?;
-        let highlighter = match file_path
-            .extension()
-            .and_then(|os_str| os_str.to_str())
-            .and_then(|ext| config.syntax_set.find_syntax_by_extension(ext))
-        {
-            Some(syntax) => Some(syntax),
-            None => config
-                .syntax_set
-                .find_syntax_by_first_line(rope.chunk_at_char(0).0),
-        }
-        .cloned()
-        .map(|syntax| {
-            let mut val = HighlightContainer::new(syntax);
-            val.highlight(&rope.slice(..), config);
-            val
-        });
+        let mut buffer = Buffer::load_scratch(&file_path, config)?;
+        buffer.disk_modified = modified_time(&file_path);
+        let canonical = std::fs::canonicalize(&file_path).unwrap_or_else(|_| file_path.clone());
+        // A broken undo file shouldn't keep the file from opening, it just starts over
+        if let Ok(Some(undo)) = UndoTree::load(&canonical, &buffer.rope) {
+            buffer.undo = undo;
+        }
+        buffer.file = Some(file_path);
+        Ok(buffer)
+    }
+
+    /// Like `load_file`, but not tied to the file, so writing it can't clobber anything
+    pub fn load_scratch(file_path: &Path, config: &Config) -> Result<Buffer> {
+        let rope = Rope::from_reader(std::fs::File::open(file_path)?)?;
+        let highlighter = highlighter_for(file_path, &rope, config);
         Ok(Buffer {
             rope,
-            cursor: Cursor::new(),
-            file: Some(file_path),
+            file: None,
             highlighter,
+            modified: false,
+            version: 0,
+            disk_modified: None,
+            warned_disk_modified: None,
+            marks: Marks::default(),
+            undo: UndoTree::default(),
         })
     }
 
+    /// Refuses to overwrite the buffer's own file if something else changed it since
     pub fn write(&mut self, file_path: Option<std::path::PathBuf>) -> Result<()> {
+        if file_path.is_none() && self.changed_on_disk() {
+            return Err(Error::ChangedOnDisk(self.name()).anyhow());
+        }
         if let Some(path) = file_path.or(self.file.take()) {
             self.rope
                 .write_to(std::io::BufWriter::new(std::fs::File::create(
                     path.as_path(),
                 )?))?;
+            self.disk_modified = modified_time(&path);
+            self.modified = false;
+            self.undo.mark_saved();
             self.file = Some(path);
             Ok(())
         } else {
@@ -86,78 +133,505 @@ impl Buffer {
         }
     }
 
-    pub fn insert_char(&mut self, config: &Config, c: char, should_step: bool) {
-        let index = self.cursor.index(&self.rope.slice(..));
+    /// Keeps the undo history for the next time the file is opened, due after each `write`
+    pub fn save_undo(&self) -> Result<()> {
+        match self.file {
+            Some(ref file) => {
+                let canonical = std::fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+                self.undo.save(&canonical, &self.rope)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// True if another program wrote the file since it was loaded or saved
+    pub fn changed_on_disk(&self) -> bool {
+        match (&self.file, self.disk_modified) {
+            (Some(file), Some(known)) => modified_time(file).map_or(false, |time| time != known),
+            _ => false,
+        }
+    }
+
+    /// Throws away the buffer's text for what is in its file now. Marks stay where they were.
+    pub fn reload(&mut self, config: &Config) -> Result<()> {
+        let file = self
+            .file
+            .as_ref()
+            .ok_or_else(|| Error::NeedFilePath.anyhow())?;
+        let rope = Rope::from_reader(std::fs::File::open(file)?)?;
+        self.disk_modified = modified_time(file);
+        // Same as opening it fresh, the old history can't be replayed over edits made elsewhere
+        let canonical = std::fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+        self.undo = UndoTree::load(&canonical, &rope)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        self.rope = rope;
+        self.modified = false;
+        self.version += 1;
+        self.rehighlight(config);
+        Ok(())
+    }
+
+    /// True only the first time a given change on disk is asked about
+    pub fn first_sight_of_disk_change(&mut self) -> bool {
+        let time = self.file.as_deref().and_then(modified_time);
+        if time == self.warned_disk_modified {
+            return false;
+        }
+        self.warned_disk_modified = time;
+        true
+    }
+
+    /// Sticks with the buffer's text over a file that changed on disk, so `:w` overwrites it
+    pub fn keep_changes(&mut self) {
+        if let Some(ref file) = self.file {
+            self.disk_modified = modified_time(file);
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.file
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "[No Name]".to_owned())
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Unnamed and never edited or marked, like the one a new tab starts with
+    pub fn is_untouched(&self) -> bool {
+        self.file.is_none() && self.version == 0 && self.marks.named().next().is_none()
+    }
+
+    /// Replaces the text with what was left in a swap file, as an unsaved change
+    pub fn recover(&mut self, rope: Rope, config: &Config) {
+        self.replace_text(rope);
+        self.marks = Marks::default();
+        self.changed(config);
+    }
+
+    /// As one change that can be undone, without moving the marks
+    fn replace_text(&mut self, rope: Rope) {
+        self.undo.commit();
+        self.undo.record(Edit::Remove {
+            index: 0,
+            text: self.rope.to_string(),
+        });
+        self.undo.record(Edit::Insert {
+            index: 0,
+            text: rope.to_string(),
+        });
+        self.undo.commit();
+        self.rope = rope;
+    }
+
+    /// Ends the change that's going on, so the next edit gets undone on its own
+    pub fn commit_undo(&mut self) {
+        self.undo.commit();
+    }
+
+    /// Puts the cursor where the last change undone was. False if there was nothing to undo.
+    pub fn undo(&mut self, cursor: &mut Cursor, config: &Config, count: usize) -> bool {
+        self.walk_undo(cursor, config, count, UndoTree::undo)
+    }
+
+    pub fn redo(&mut self, cursor: &mut Cursor, config: &Config, count: usize) -> bool {
+        self.walk_undo(cursor, config, count, UndoTree::redo)
+    }
+
+    fn walk_undo(
+        &mut self,
+        cursor: &mut Cursor,
+        config: &Config,
+        count: usize,
+        step: fn(&mut UndoTree) -> Option<Vec<Edit>>,
+    ) -> bool {
+        let mut position = None;
+        for _ in 0..count {
+            let edits = match step(&mut self.undo) {
+                Some(edits) => edits,
+                None => break,
+            };
+            position = edits.first().map(Edit::index).or(position);
+            for edit in edits {
+                self.apply(&edit);
+            }
+        }
+        let index = match position {
+            Some(index) => index,
+            None => return false,
+        };
+        cursor.set_position(self.point_at(index), &self.rope.slice(..));
+        self.changed(config);
+        self.modified = !self.undo.at_saved();
+        true
+    }
+
+    pub fn insert_char(
+        &mut self,
+        cursor: &mut Cursor,
+        config: &Config,
+        c: char,
+        should_step: bool,
+    ) {
+        let index = cursor.index(&self.rope.slice(..));
         match c {
             '\t' => {
-                self.rope.insert(index, "    ");
+                self.insert(index, "    ");
 
                 if should_step {
-                    self.cursor.step(Direction::Right, &self.rope.slice(..));
-                    self.cursor.step(Direction::Right, &self.rope.slice(..));
-                    self.cursor.step(Direction::Right, &self.rope.slice(..));
-                    self.cursor.step(Direction::Right, &self.rope.slice(..));
+                    cursor.step(Direction::Right, &self.rope.slice(..));
+                    cursor.step(Direction::Right, &self.rope.slice(..));
+                    cursor.step(Direction::Right, &self.rope.slice(..));
+                    cursor.step(Direction::Right, &self.rope.slice(..));
                 }
             }
             '\n' => {
-                let line: Cow<str> = self.rope.line(self.cursor.row()).into();
+                let line: Cow<str> = self.rope.line(cursor.row()).into();
                 let space_count = line.len() - line.trim_start().len();
-                self.rope.insert_char(index, '\n');
-                for _ in 0..space_count {
-                    self.rope.insert_char(index + 1, ' ');
-                }
+                self.insert(index, &format!("\n{}", " ".repeat(space_count)));
                 if should_step {
                     let slice = self.rope.slice(..);
-                    self.cursor.step(Direction::Right, &slice);
+                    cursor.step(Direction::Right, &slice);
                     for _ in 0..space_count {
-                        self.cursor.step(Direction::Right, &slice);
+                        cursor.step(Direction::Right, &slice);
                     }
                 }
             }
             c => {
-                self.rope.insert_char(index, c);
+                self.insert(index, c.encode_utf8(&mut [0; 4]));
                 if should_step {
-                    self.cursor.step(Direction::Right, &self.rope.slice(..));
+                    cursor.step(Direction::Right, &self.rope.slice(..));
                 }
             }
         }
+        self.changed(config);
+    }
+
+    pub fn delete_char(
+        &mut self,
+        cursor: &mut Cursor,
+        config: &Config,
+        direction: DeleteDirection,
+        count: usize,
+        join_lines: bool,
+    ) {
+        let char_index = cursor.index(&self.rope.slice(..));
+        let range = delete_range(
+            &self.rope.slice(..),
+            char_index,
+            direction,
+            count,
+            join_lines,
+        );
+        // Nothing to delete at either end of the line
+        if range.is_empty() {
+            return;
+        }
+        self.remove(range.clone());
+        if direction == DeleteDirection::Before {
+            let position = Point::from_index(range.start, &self.rope.slice(..));
+            cursor.set_position(position, &self.rope.slice(..));
+        }
+        self.changed(config);
+    }
+
+    /// Deletes from the cursor to wherever `jump_type` takes it and returns what was removed
+    pub fn delete_motion(
+        &mut self,
+        cursor: &mut Cursor,
+        config: &Config,
+        jump_type: JumpType,
+        line_count: usize,
+        count: usize,
+    ) -> String {
+        // Like vim, motions that land on a character take it with them. `EndOfLine`
+        // lands on the line break, which stays, and `F`/`T` leave the cursor's character.
+        let inclusive = match jump_type {
+            JumpType::EndOfWord | JumpType::MatchingBracket => true,
+            JumpType::Find(ref search) => search.forward,
+            _ => false,
+        };
+        let rope = self.rope.slice(..);
+        let start = cursor.index(&rope);
+        let mut moved = cursor.clone();
+        self.jump(&mut moved, jump_type, line_count, count);
+        let end = moved.index(&rope);
+        if start == end {
+            return String::new();
+        }
+        let (from, to) = (start.min(end), start.max(end));
+        let to = if inclusive {
+            (to + 1).min(rope.len_chars())
+        } else {
+            to
+        };
+        let text = rope.slice(from..to).to_string();
+        self.remove(from..to);
+        let position = Point::from_index(from, &self.rope.slice(..));
+        cursor.set_position(position, &self.rope.slice(..));
+        self.changed(config);
+        text
+    }
+
+    /// Removes `count` lines starting at the cursor's and returns them
+    pub fn delete_lines(&mut self, cursor: &mut Cursor, config: &Config, count: usize) -> String {
+        let first = cursor.row();
+        let last = (first + count).min(self.rope.len_lines());
+        let mut start = self.rope.line_to_char(first);
+        let end = self.rope.line_to_char(last);
+        if last == self.rope.len_lines() && start > 0 {
+            // No line break after the last line, so take the one before it instead
+            start -= 1;
+        }
+        let mut text = self.rope.slice(start..end).to_string();
+        if start < self.rope.line_to_char(first) {
+            text = format!("{}\n", &text[1..]);
+        } else if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        self.remove(start..end);
+        let line = first.min(self.rope.len_lines().saturating_sub(1));
+        cursor.set_position(
+            Point {
+                x: 0,
+                y: line as u16,
+            },
+            &self.rope.slice(..),
+        );
+        cursor.jump(JumpType::StartOfLine, &self.rope.slice(..), 0, 1);
+        self.changed(config);
+        text
+    }
+
+    /// Every edit goes through this or `remove` so the marks follow the text and it can be undone
+    fn insert(&mut self, index: usize, text: &str) {
+        let edit = Edit::Insert {
+            index,
+            text: text.to_owned(),
+        };
+        self.apply(&edit);
+        self.undo.record(edit);
+    }
+
+    fn remove(&mut self, range: Range<usize>) {
+        let edit = Edit::Remove {
+            index: range.start,
+            text: self.rope.slice(range).to_string(),
+        };
+        self.apply(&edit);
+        self.undo.record(edit);
+    }
+
+    fn apply(&mut self, edit: &Edit) {
+        match edit {
+            Edit::Insert { index, text } => {
+                self.rope.insert(*index, text);
+                self.marks
+                    .inserted(*index, text.chars().count(), &self.rope.slice(..));
+            }
+            Edit::Remove { index, text } => {
+                let range = *index..*index + text.chars().count();
+                self.rope.remove(range.clone());
+                self.marks.removed(range, &self.rope.slice(..));
+            }
+        }
+    }
+
+    fn changed(&mut self, config: &Config) {
+        self.modified = true;
+        self.version += 1;
+        self.rehighlight(config);
+    }
+
+    /// For when the text stayed the same but the theme didn't
+    pub fn rehighlight(&mut self, config: &Config) {
         if let Some(ref mut highlighter) = self.highlighter {
             highlighter.highlight(&self.rope.slice(..), config);
         }
     }
 
-    pub fn delete_char(&mut self, config: &Config, direction: DeleteDirection) {
-        let char_index = self.cursor.index(&self.rope.slice(..));
-        match direction {
-            DeleteDirection::Before => {
-                if char_index > 0 {
-                    self.rope.remove(char_index - 1..char_index);
-                    self.cursor.step(Direction::Left, &self.rope.slice(..));
-                }
+    /// Inserts `text` as is at the cursor and moves past it
+    pub fn insert_str(&mut self, cursor: &mut Cursor, config: &Config, text: &str) {
+        let index = cursor.index(&self.rope.slice(..));
+        self.insert(index, text);
+        let end = Point::from_index(index + text.chars().count(), &self.rope.slice(..));
+        cursor.set_position(end, &self.rope.slice(..));
+        self.changed(config);
+    }
+
+    /// Vim style put. Text ending in a line break goes on its own line,
+    /// anything else goes right next to the cursor.
+    pub fn paste(&mut self, cursor: &mut Cursor, config: &Config, text: &str, after: bool) {
+        if text.is_empty() {
+            return;
+        }
+        if text.ends_with('\n') {
+            let line = if after {
+                cursor.row() + 1
+            } else {
+                cursor.row()
+            };
+            let ends_with_newline =
+                self.rope.len_chars() == 0 || self.rope.char(self.rope.len_chars() - 1) == '\n';
+            if line >= self.rope.len_lines() && !ends_with_newline {
+                // Last line has no line break to put the text after
+                let index = self.rope.len_chars();
+                self.insert(index, &format!("\n{}", &text[..text.len() - 1]));
+            } else {
+                let index = self.rope.line_to_char(line.min(self.rope.len_lines()));
+                self.insert(index, text);
+            }
+            let line = line.min(self.rope.len_lines().saturating_sub(1));
+            cursor.set_position(
+                Point {
+                    x: 0,
+                    y: line as u16,
+                },
+                &self.rope.slice(..),
+            );
+            cursor.jump(JumpType::StartOfLine, &self.rope.slice(..), 0, 1);
+            self.changed(config);
+        } else {
+            let index = cursor.index(&self.rope.slice(..));
+            let on_char = index < self.rope.len_chars() && self.rope.char(index) != '\n';
+            if after && on_char {
+                cursor.step(Direction::Right, &self.rope.slice(..));
             }
-            DeleteDirection::After => {
-                if char_index < self.rope.len_chars() {
-                    self.rope.remove(char_index..=char_index);
+            self.insert_str(cursor, config, text);
+            // Ends up on the last character that was put
+            cursor.step(Direction::Left, &self.rope.slice(..));
+        }
+    }
+
+    /// The selected text, or the cursor's line with its line break
+    pub fn yank_text(&self, cursor: &Cursor) -> String {
+        let rope = self.rope.slice(..);
+        match cursor.selection(&rope) {
+            Some(selection) => rope.slice(selection).to_string(),
+            None => {
+                let line = rope.line(cursor.row()).to_string();
+                if line.ends_with('\n') {
+                    line
+                } else {
+                    line + "\n"
                 }
             }
-        };
-        if let Some(ref mut highlighter) = self.highlighter {
-            highlighter.highlight(&self.rope.slice(..), config);
         }
     }
 
-    pub fn render(&self, ui: &imgui::Ui) {
-        let _visible_lines = get_visible_lines(ui);
-        let line_len = self.rope.len_lines();
-        let line_offset = log10(line_len);
-        let line_offset_px = 5. + line_offset as f32 * 10.;
+    pub fn filetype(&self) -> Option<&str> {
+        self.highlighter
+            .as_ref()
+            .map(|highlighter| highlighter.syntax_name())
+    }
+
+    /// Guessed from the first line, mixed line endings aren't worth caring about
+    pub fn line_ending(&self) -> &'static str {
+        let first_line: Cow<str> = self.rope.line(0).into();
+        if first_line.ends_with("\r\n") {
+            "dos"
+        } else {
+            "unix"
+        }
+    }
+
+    /// Cheap to clone, the chunks are shared
+    pub fn rope(&self) -> &Rope {
+        &self.rope
+    }
+
+    pub fn slice(&self) -> RopeSlice {
+        self.rope.slice(..)
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    pub fn file(&self) -> Option<&std::path::Path> {
+        self.file.as_deref()
+    }
 
+    pub fn set_mark(&mut self, name: char, cursor: &Cursor) {
+        let index = cursor.index(&self.rope.slice(..));
+        self.marks.set(name, index);
+    }
+
+    /// File marks only live in one buffer at a time
+    pub fn remove_mark(&mut self, name: char) {
+        self.marks.remove(name);
+    }
+
+    pub fn mark(&self, name: char) -> Option<Point> {
+        self.marks.get(name).map(|index| self.point_at(index))
+    }
+
+    /// Sorted by name
+    pub fn marks(&self) -> Vec<(char, Point)> {
+        let mut marks: Vec<(char, Point)> = self
+            .marks
+            .named()
+            .map(|(name, index)| (name, self.point_at(index)))
+            .collect();
+        marks.sort_by_key(|(name, _)| *name);
+        marks
+    }
+
+    /// `g;`
+    pub fn older_change(&mut self, count: usize) -> Option<Point> {
+        self.marks
+            .older_change(count)
+            .map(|index| self.point_at(index))
+    }
+
+    /// `g,`
+    pub fn newer_change(&mut self, count: usize) -> Option<Point> {
+        self.marks
+            .newer_change(count)
+            .map(|index| self.point_at(index))
+    }
+
+    fn point_at(&self, index: usize) -> Point {
+        Point::from_index(index.min(self.rope.len_chars()), &self.rope.slice(..))
+    }
+
+    /// False for anything in a string or comment, everything counts without a syntax
+    fn is_code(&self, index: usize) -> bool {
+        match self.highlighter {
+            Some(ref highlighter) => {
+                let line = self.rope.char_to_line(index);
+                let byte = self.rope.char_to_byte(index) - self.rope.line_to_byte(line);
+                highlighter.is_code(line, byte)
+            }
+            None => true,
+        }
+    }
+
+    /// `line_offset_px` is the space taken up by the gutter
+    pub fn render(
+        &self,
+        ui: &imgui::Ui,
+        cursor: &Cursor,
+        line_offset_px: f32,
+        focused: bool,
+        rainbow: bool,
+    ) {
+        let rope = self.rope.slice(..);
         ui.group(|| {
             ui.set_cursor_pos([0., 0.]);
             ui.new_line();
             ui.indent_by(line_offset_px);
             if let Some(ref highlighter) = self.highlighter {
-                highlighter.render(&self.rope.slice(..), ui);
+                highlighter.render(&rope, ui, rainbow);
             } else {
                 for line in self.rope.lines() {
                     let text: Cow<str> = line.into();
@@ -165,29 +639,91 @@ impl Buffer {
                 }
             }
         });
-        ui.group(|| {
-            ui.set_cursor_pos([0., 0.]);
-            ui.new_line();
-            ui.indent_by(5.);
-            for line in 0..line_len {
-                ui.text(&format!("{}", line + 1));
+        let index = cursor.clamped(&rope).index(&rope);
+        let on_bracket = index < rope.len_chars() && brackets::is_bracket(rope.char(index));
+        if on_bracket && self.is_code(index) {
+            if let Some(matching) = brackets::matching(&rope, index, &|i| self.is_code(i)) {
+                let point = Point::from_index(matching, &rope);
+                outline_char(ui, &rope, line_offset_px, point, MATCHING_BRACKET_COLOR);
             }
-        });
-        self.cursor.render(ui, line_offset_px, &self.rope.slice(..));
+        }
+        cursor.render(ui, line_offset_px, &rope, focused);
     }
 
-    pub fn step(&mut self, direction: Direction) {
-        self.cursor.step(direction, &self.rope.slice(..));
+    pub fn step(&self, cursor: &mut Cursor, direction: Direction) {
+        cursor.step(direction, &self.rope.slice(..));
     }
 
-    pub fn jump(&mut self, jump_type: JumpType, line_count: usize) {
-        self.cursor
-            .jump(jump_type, &self.rope.slice(..), line_count);
+    pub fn jump(&self, cursor: &mut Cursor, jump_type: JumpType, line_count: usize, count: usize) {
+        let rope = self.rope.slice(..);
+        match jump_type {
+            JumpType::MatchingBracket => {
+                let index = cursor.index(&rope);
+                if let Some(matching) = brackets::find_match(&rope, index, &|i| self.is_code(i)) {
+                    cursor.set_position(Point::from_index(matching, &rope), &rope);
+                }
+            }
+            jump_type => cursor.jump(jump_type, &rope, line_count, count),
+        }
     }
 }
 
+/// Picks the syntax by extension, then by the first line, and highlights `rope` with it
+pub fn highlighter_for(
+    file_path: &std::path::Path,
+    rope: &Rope,
+    config: &Config,
+) -> Option<HighlightContainer> {
+    match file_path
+        .extension()
+        .and_then(|os_str| os_str.to_str())
+        .and_then(|ext| config.syntax_set.find_syntax_by_extension(ext))
+    {
+        Some(syntax) => Some(syntax),
+        None => config
+            .syntax_set
+            .find_syntax_by_first_line(rope.chunk_at_char(0).0),
+    }
+    .cloned()
+    .map(|syntax| {
+        let mut val = HighlightContainer::new(syntax);
+        val.highlight(&rope.slice(..), config);
+        val
+    })
+}
+
 pub fn get_visible_lines(ui: &imgui::Ui) -> usize {
     let window_height = ui.window_size()[1];
     let line_height = ui.text_line_height_with_spacing();
-    ((window_height) / line_height) as usize - 2
+    (((window_height) / line_height) as usize).saturating_sub(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counted_x_stays_on_its_line() {
+        let rope = Rope::from_str("ab\ncd\n");
+        let rope = rope.slice(..);
+        assert_eq!(
+            delete_range(&rope, 1, DeleteDirection::After, 10, false),
+            1..2
+        );
+        assert_eq!(
+            delete_range(&rope, 4, DeleteDirection::Before, 10, false),
+            3..4
+        );
+        assert!(delete_range(&rope, 2, DeleteDirection::After, 1, false).is_empty());
+        assert!(delete_range(&rope, 3, DeleteDirection::Before, 1, false).is_empty());
+    }
+
+    #[test]
+    fn backspace_joins_lines() {
+        let rope = Rope::from_str("ab\ncd\n");
+        assert_eq!(
+            delete_range(&rope.slice(..), 3, DeleteDirection::Before, 1, true),
+            2..3
+        );
+    }
 }
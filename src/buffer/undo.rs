@@ -0,0 +1,267 @@
+use crate::config_file::{flatten_path, state_dir};
+
+use anyhow::Result;
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Histories of the files edited least recently are deleted past this
+const MAX_UNDO_FILES: usize = 200;
+
+/// One step of a change, char indices like the marks
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Edit {
+    Insert { index: usize, text: String },
+    Remove { index: usize, text: String },
+}
+
+impl Edit {
+    fn inverse(&self) -> Edit {
+        match self {
+            Edit::Insert { index, text } => Edit::Remove {
+                index: *index,
+                text: text.clone(),
+            },
+            Edit::Remove { index, text } => Edit::Insert {
+                index: *index,
+                text: text.clone(),
+            },
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            Edit::Insert { index, .. } | Edit::Remove { index, .. } => *index,
+        }
+    }
+}
+
+/// Everything one command or insert session did
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Change {
+    parent: usize,
+    edits: Vec<Edit>,
+    /// The child redo goes to, whichever was made or undone last
+    redo: Option<usize>,
+}
+
+/// Every state the text was in, branching wherever new edits followed an undo, like vim's
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UndoTree {
+    /// The first one is the text as it was loaded and has no edits
+    changes: Vec<Change>,
+    current: usize,
+    /// The change the file on disk matches
+    saved: Option<usize>,
+    /// Edits of the change that's still going on
+    #[serde(skip)]
+    pending: Vec<Edit>,
+}
+
+impl Default for UndoTree {
+    fn default() -> UndoTree {
+        UndoTree {
+            changes: vec![Change {
+                parent: 0,
+                edits: Vec::new(),
+                redo: None,
+            }],
+            current: 0,
+            saved: Some(0),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl UndoTree {
+    pub fn record(&mut self, edit: Edit) {
+        // Typing goes in a character at a time, which would make for a lot of edits
+        if let (
+            Some(Edit::Insert { index, text }),
+            Edit::Insert {
+                index: next,
+                text: more,
+            },
+        ) = (self.pending.last_mut(), &edit)
+        {
+            if *index + text.chars().count() == *next {
+                text.push_str(more);
+                return;
+            }
+        }
+        self.pending.push(edit);
+    }
+
+    /// Closes the change that's going on, the next edit starts a new one
+    pub fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let change = Change {
+            parent: self.current,
+            edits: std::mem::take(&mut self.pending),
+            redo: None,
+        };
+        self.changes.push(change);
+        let new = self.changes.len() - 1;
+        self.changes[self.current].redo = Some(new);
+        self.current = new;
+    }
+
+    /// The edits that take the text back to before the current change, in order
+    pub fn undo(&mut self) -> Option<Vec<Edit>> {
+        self.commit();
+        if self.current == 0 {
+            return None;
+        }
+        let undone = self.current;
+        let change = &self.changes[undone];
+        let edits = change.edits.iter().rev().map(Edit::inverse).collect();
+        self.current = change.parent;
+        self.changes[self.current].redo = Some(undone);
+        Some(edits)
+    }
+
+    /// The edits of the change that was undone last, in order
+    pub fn redo(&mut self) -> Option<Vec<Edit>> {
+        self.commit();
+        let next = self.changes[self.current].redo?;
+        self.current = next;
+        Some(self.changes[next].edits.clone())
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.commit();
+        self.saved = Some(self.current);
+    }
+
+    /// True when undo or redo got back to what's on disk
+    pub fn at_saved(&self) -> bool {
+        self.pending.is_empty() && self.saved == Some(self.current)
+    }
+
+    /// Kept next to the swap files, for the canonical `file`
+    fn path(file: &Path) -> Option<PathBuf> {
+        state_dir().map(|dir| dir.join("undo").join(flatten_path(file)))
+    }
+
+    /// Nothing when there's no history for `file` or it was changed without the editor
+    pub fn load(file: &Path, rope: &Rope) -> Result<Option<UndoTree>> {
+        let path = match UndoTree::path(file) {
+            Some(path) if path.exists() => path,
+            _ => return Ok(None),
+        };
+        let undo_file: UndoFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if undo_file.hash != content_hash(rope) {
+            return Ok(None);
+        }
+        Ok(Some(undo_file.tree))
+    }
+
+    /// `rope` has to be what was just written to `file`, and marked saved
+    pub fn save(&self, file: &Path, rope: &Rope) -> Result<()> {
+        let path = match UndoTree::path(file) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let undo_file = UndoFile {
+            hash: content_hash(rope),
+            tree: self.clone(),
+        };
+        std::fs::write(&path, serde_json::to_string(&undo_file)?)?;
+        match path.parent() {
+            Some(dir) => prune(dir),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Keeps the `MAX_UNDO_FILES` newest histories in `dir`
+fn prune(dir: &Path) -> Result<()> {
+    let mut files: Vec<(SystemTime, PathBuf)> = std::fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    if files.len() <= MAX_UNDO_FILES {
+        return Ok(());
+    }
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    for (_, path) in &files[MAX_UNDO_FILES..] {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct UndoFile {
+    /// Of the text the tree's saved change ends with
+    hash: u64,
+    tree: UndoTree,
+}
+
+/// FNV-1a, unlike std's hashers it's guaranteed to give the same number in the next build
+fn content_hash(rope: &Rope) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in rope.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(index: usize, text: &str) -> Edit {
+        Edit::Insert {
+            index,
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn typing_is_one_edit() {
+        let mut tree = UndoTree::default();
+        tree.record(insert(0, "a"));
+        tree.record(insert(1, "b"));
+        let remove = Edit::Remove {
+            index: 0,
+            text: "ab".to_owned(),
+        };
+        assert_eq!(tree.undo(), Some(vec![remove]));
+        assert_eq!(tree.undo(), None);
+    }
+
+    #[test]
+    fn redo_follows_the_last_branch() {
+        let mut tree = UndoTree::default();
+        tree.record(insert(0, "a"));
+        tree.commit();
+        tree.undo();
+        tree.record(insert(0, "b"));
+        tree.commit();
+        tree.undo();
+        assert_eq!(tree.redo(), Some(vec![insert(0, "b")]));
+        assert_eq!(tree.redo(), None);
+    }
+
+    #[test]
+    fn saved_state() {
+        let mut tree = UndoTree::default();
+        tree.record(insert(0, "a"));
+        assert!(!tree.at_saved());
+        tree.undo();
+        assert!(tree.at_saved());
+    }
+}
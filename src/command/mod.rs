@@ -1,7 +1,7 @@
 use crate::{
     error::Error,
     mode::Mode,
-    msg::{Cmd, Msg},
+    msg::{Cmd, DiskAction, Msg, PickerKind, SplitDirection, SwapAction},
     text_buffer::TextBuffer,
 };
 
@@ -14,58 +14,345 @@ pub struct CommandBuffer {
     buffer: TextBuffer,
 }
 
+/// One ex-command, `execute` and the command palette both go through these
+pub struct Command {
+    /// The full name comes first, then the abbreviations
+    pub names: &'static [&'static str],
+    /// The palette leaves these on the command line to finish
+    pub takes_arg: bool,
+    run: fn(&[&str], &EventLoopProxy<Msg>) -> Result<(), Error>,
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        self.names[0]
+    }
+}
+
+fn send(msg_sender: &EventLoopProxy<Msg>, cmd: Cmd) -> Result<(), Error> {
+    msg_sender
+        .send_event(Msg::Cmd(cmd))
+        .expect("sending command");
+    Ok(())
+}
+
+fn load_file(args: &[&str], msg_sender: &EventLoopProxy<Msg>) -> Result<(), Error> {
+    match args.first() {
+        Some(file) => send(msg_sender, Cmd::LoadFile(std::path::PathBuf::from(file))),
+        None => Err(Error::MissingArg),
+    }
+}
+
+/// Optionally opens a file in the new view
+fn split(
+    direction: SplitDirection,
+    args: &[&str],
+    msg_sender: &EventLoopProxy<Msg>,
+) -> Result<(), Error> {
+    send(msg_sender, Cmd::SplitView(direction))?;
+    if !args.is_empty() {
+        load_file(args, msg_sender)?;
+    }
+    Ok(())
+}
+
+fn resize(
+    direction: SplitDirection,
+    args: &[&str],
+    msg_sender: &EventLoopProxy<Msg>,
+) -> Result<(), Error> {
+    match args.first().map(|arg| (arg, arg.parse::<i16>())) {
+        Some((_, Ok(percent))) => send(msg_sender, Cmd::ResizeView(direction, percent)),
+        Some((arg, Err(_))) => Err(Error::InvalidArg((*arg).to_owned())),
+        None => Err(Error::MissingArg),
+    }
+}
+
+fn map(
+    mode: Mode,
+    remap: bool,
+    args: &[&str],
+    msg_sender: &EventLoopProxy<Msg>,
+) -> Result<(), Error> {
+    match args.split_first() {
+        Some((lhs, rhs)) if !rhs.is_empty() => send(
+            msg_sender,
+            Cmd::Map(mode, (*lhs).to_owned(), rhs.join(" "), remap),
+        ),
+        _ => Err(Error::MissingArg),
+    }
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        names: &["w"],
+        takes_arg: false,
+        run: |args, msg_sender| {
+            let path = if args.is_empty() {
+                None
+            } else {
+                Some(std::path::PathBuf::from(args.concat()))
+            };
+            send(msg_sender, Cmd::WriteBuffer(path))
+        },
+    },
+    Command {
+        names: &["w!"],
+        takes_arg: false,
+        run: |_, msg_sender| {
+            // Keeping the buffer's version is what makes the write go through
+            send(msg_sender, Cmd::Disk(DiskAction::Keep))?;
+            send(msg_sender, Cmd::WriteBuffer(None))
+        },
+    },
+    Command {
+        names: &["q"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::Quit),
+    },
+    Command {
+        names: &["edit"],
+        takes_arg: true,
+        run: load_file,
+    },
+    Command {
+        names: &["edit!", "e!"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::Disk(DiskAction::Reload)),
+    },
+    Command {
+        names: &["split", "sp"],
+        takes_arg: false,
+        run: |args, msg_sender| split(SplitDirection::Horizontal, args, msg_sender),
+    },
+    Command {
+        names: &["vsplit", "vs"],
+        takes_arg: false,
+        run: |args, msg_sender| split(SplitDirection::Vertical, args, msg_sender),
+    },
+    Command {
+        names: &["close", "clo"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::CloseView),
+    },
+    Command {
+        names: &["only", "on"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OnlyView),
+    },
+    Command {
+        names: &["resize", "res"],
+        takes_arg: true,
+        run: |args, msg_sender| resize(SplitDirection::Horizontal, args, msg_sender),
+    },
+    Command {
+        names: &["vresize"],
+        takes_arg: true,
+        run: |args, msg_sender| resize(SplitDirection::Vertical, args, msg_sender),
+    },
+    Command {
+        names: &["messages", "mes"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::ShowMessages),
+    },
+    Command {
+        names: &["set", "se"],
+        takes_arg: true,
+        run: |args, msg_sender| {
+            if args.is_empty() {
+                return Err(Error::MissingArg);
+            }
+            for arg in args {
+                send(msg_sender, Cmd::SetOption((*arg).to_owned()))?;
+            }
+            Ok(())
+        },
+    },
+    Command {
+        names: &["tabnew", "tabe", "tabedit"],
+        takes_arg: false,
+        run: |args, msg_sender| {
+            send(msg_sender, Cmd::NewTab)?;
+            if !args.is_empty() {
+                load_file(args, msg_sender)?;
+            }
+            Ok(())
+        },
+    },
+    Command {
+        names: &["tabclose", "tabc"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::CloseTab),
+    },
+    Command {
+        names: &["tabnext", "tabn"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::NextTab),
+    },
+    Command {
+        names: &["tabprevious", "tabp"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::PrevTab),
+    },
+    Command {
+        names: &["map", "nmap"],
+        takes_arg: true,
+        run: |args, msg_sender| map(Mode::Normal, true, args, msg_sender),
+    },
+    Command {
+        names: &["imap"],
+        takes_arg: true,
+        run: |args, msg_sender| map(Mode::Insert, true, args, msg_sender),
+    },
+    Command {
+        names: &["cmap"],
+        takes_arg: true,
+        run: |args, msg_sender| map(Mode::Command, true, args, msg_sender),
+    },
+    Command {
+        names: &["noremap", "nnoremap", "nn"],
+        takes_arg: true,
+        run: |args, msg_sender| map(Mode::Normal, false, args, msg_sender),
+    },
+    Command {
+        names: &["inoremap", "ino"],
+        takes_arg: true,
+        run: |args, msg_sender| map(Mode::Insert, false, args, msg_sender),
+    },
+    Command {
+        names: &["cnoremap", "cno"],
+        takes_arg: true,
+        run: |args, msg_sender| map(Mode::Command, false, args, msg_sender),
+    },
+    Command {
+        names: &["files"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OpenPicker(PickerKind::Files)),
+    },
+    Command {
+        names: &["buffers", "ls"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OpenPicker(PickerKind::Buffers)),
+    },
+    Command {
+        names: &["commands"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OpenPicker(PickerKind::Commands)),
+    },
+    Command {
+        names: &["lines"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OpenPicker(PickerKind::Lines)),
+    },
+    Command {
+        names: &["recent", "oldfiles", "ol"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OpenPicker(PickerKind::Recent)),
+    },
+    Command {
+        names: &["registers", "reg"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OpenPicker(PickerKind::Registers)),
+    },
+    Command {
+        names: &["marks"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OpenPicker(PickerKind::Marks)),
+    },
+    Command {
+        names: &["colorscheme", "colo"],
+        takes_arg: false,
+        run: |args, msg_sender| {
+            let cmd = if args.is_empty() {
+                Cmd::OpenPicker(PickerKind::Themes)
+            } else {
+                // Some of the default theme names have spaces in them
+                Cmd::SetTheme(args.join(" "))
+            };
+            send(msg_sender, cmd)
+        },
+    },
+    Command {
+        names: &["grep", "gr"],
+        takes_arg: true,
+        run: |args, msg_sender| {
+            send(msg_sender, Cmd::OpenPicker(PickerKind::Grep))?;
+            if !args.is_empty() {
+                send(msg_sender, Cmd::InsertText(args.join(" ")))?;
+            }
+            Ok(())
+        },
+    },
+    Command {
+        names: &["copen", "cope"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::OpenPicker(PickerKind::Quickfix)),
+    },
+    Command {
+        names: &["cnext", "cn"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::NextQuickfix),
+    },
+    Command {
+        names: &["cprevious", "cp"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::PrevQuickfix),
+    },
+    Command {
+        names: &["recover", "rec"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::Swap(SwapAction::Recover)),
+    },
+    Command {
+        names: &["diffswap"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::Swap(SwapAction::Diff)),
+    },
+    Command {
+        names: &["deleteswap"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::Swap(SwapAction::Delete)),
+    },
+    Command {
+        names: &["keep"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::Disk(DiskAction::Keep)),
+    },
+    Command {
+        names: &["diffdisk"],
+        takes_arg: false,
+        run: |_, msg_sender| send(msg_sender, Cmd::Disk(DiskAction::Diff)),
+    },
+];
+
+/// Runs an ex-command (without the leading `:`)
+pub fn execute(command: &str, msg_sender: EventLoopProxy<Msg>) -> Result<()> {
+    // Command names are case insensitive cause I have always hated my life when I accidentally
+    // hold down shift while trying to save files
+    let mut words = command.split_whitespace();
+    let name = match words.next() {
+        Some(name) => name.to_lowercase(),
+        None => return Ok(()),
+    };
+    let args: Vec<&str> = words.collect();
+    let result = match COMMANDS
+        .iter()
+        .find(|command| command.names.contains(&name.as_str()))
+    {
+        Some(command) => (command.run)(&args, &msg_sender),
+        None => Err(Error::UnknownCommand(name)),
+    };
+    result.map_err(|cmd_err| cmd_err.into())
+}
+
 impl CommandBuffer {
     pub fn clear(&mut self) {
         self.buffer.clear();
     }
     fn run_command(&mut self, msg_sender: EventLoopProxy<Msg>) -> Result<()> {
-        msg_sender
-            .send_event(Msg::Cmd(Cmd::ChangeMode(Mode::Normal)))
-            .expect("Changing to normal mode");
-        // Case insensitive cause I have always hated my life when I accidentally
-        // hold down shift while trying to save files
-        let command = self.buffer.as_str().to_lowercase();
-        let mut words = command.split_whitespace();
-        let result = match words.next() {
-            Some("q") => {
-                msg_sender
-                    .send_event(Msg::Cmd(Cmd::Quit))
-                    .expect("Sending quit message");
-                Ok(())
-            }
-            Some("w") => {
-                let maybe_path = {
-                    let rest = words.fold(String::new(), |mut acc, word| {
-                        acc.push_str(&word);
-                        acc
-                    });
-                    if rest.len() == 0 {
-                        None
-                    } else {
-                        Some(std::path::PathBuf::from(rest))
-                    }
-                };
-                msg_sender
-                    .send_event(Msg::Cmd(Cmd::WriteBuffer(maybe_path)))
-                    .expect("Sending write message");
-                Ok(())
-            }
-            Some("edit") => {
-                let maybe_file = words.next();
-                if let Some(file) = maybe_file {
-                    msg_sender
-                        .send_event(Msg::Cmd(Cmd::LoadFile(std::path::PathBuf::from(file))))
-                        .expect("sending load file command");
-                    Ok(())
-                } else {
-                    Err(Error::MissingArg)
-                }
-            }
-            Some(buffer) => Err(Error::UnknownCommand(buffer.to_owned())),
-            None => Ok(()),
-        };
+        let command = self.buffer.as_str().to_owned();
         self.buffer.clear();
-        result.map_err(|cmd_err| cmd_err.into())
+        execute(&command, msg_sender)
     }
     pub fn handle_command(&mut self, cmd: Cmd, msg_sender: EventLoopProxy<Msg>) -> Result<bool> {
         Ok(match cmd {
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// `$XDG_CONFIG_HOME/editor`, falling back to `~/.config/editor`
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("editor"))
+}
+
+/// `$XDG_STATE_HOME/editor`, falling back to `~/.local/state/editor`. For things
+/// the editor keeps track of itself, like file history.
+pub fn state_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })
+        .map(|dir| dir.join("editor"))
+}
+
+/// Turns a whole path into one file name, like vim's `directory` with a trailing `//`
+pub fn flatten_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| if std::path::is_separator(c) { '%' } else { c })
+        .collect()
+}
+
+/// User settings read from `config.json`, everything is optional
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub statusline: Option<String>,
+    /// Key notation, defaults to `\`
+    pub leader: Option<String>,
+    /// Mode name to `lhs -> rhs` in key notation. A `rhs` like `:w` runs an ex-command.
+    pub keymaps: HashMap<String, HashMap<String, String>>,
+    /// Applied like `:set` arguments, e.g. `["relativenumber", "timeoutlen=500"]`
+    pub set: Vec<String>,
+}
+
+impl ConfigFile {
+    pub fn load(path: Option<PathBuf>) -> Result<ConfigFile> {
+        let path = match path.or_else(|| config_dir().map(|dir| dir.join("config.json"))) {
+            Some(path) if path.exists() => path,
+            _ => return Ok(ConfigFile::default()),
+        };
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
@@ -4,9 +4,87 @@ use crate::{
 };
 use ropey::RopeSlice;
 
+use std::{borrow::Cow, ops::Range};
+
+/// Space between the gutter and the first column of text
+const TEXT_PADDING: f32 = 8.;
+const SELECTION_COLOR: [f32; 4] = [0.3, 0.5, 0.9, 0.3];
+
+/// A line without its line break
+fn line_text<'a>(rope: &RopeSlice<'a>, line: usize) -> Cow<'a, str> {
+    let text: Cow<str> = rope.line(line).into();
+    match text {
+        Cow::Borrowed(text) => Cow::Borrowed(text.trim_end_matches(&['\r', '\n'][..])),
+        Cow::Owned(text) => Cow::Owned(text.trim_end_matches(&['\r', '\n'][..]).to_owned()),
+    }
+}
+
+/// Pixels from the start of the text to `col`. Drawing and mouse hit testing
+/// both go through here so they always agree.
+fn column_x(ui: &imgui::Ui, line: &str, col: usize) -> f32 {
+    let im_str = imgui::ImString::new(line.chars().take(col).collect::<String>());
+    ui.calc_text_size(&im_str, false, 0.)[0]
+}
+
+/// Left, top, right and bottom of the character at `point`, relative to the window
+fn char_rect(ui: &imgui::Ui, rope: &RopeSlice, horizontal_offset: f32, point: Point) -> [f32; 4] {
+    let line_height = ui.text_line_height_with_spacing();
+    let line = line_text(rope, point.y as usize);
+    let left = column_x(ui, &line, point.x as usize) + horizontal_offset + TEXT_PADDING;
+    let top = (point.y + 1) as f32 * line_height - ui.scroll_y();
+    [left, top, left + 7., top + line_height]
+}
+
+/// Draws a box around the character at `point`, for things like the matching bracket
+pub fn outline_char(
+    ui: &imgui::Ui,
+    rope: &RopeSlice,
+    horizontal_offset: f32,
+    point: Point,
+    color: [f32; 4],
+) {
+    let [left, top, right, bottom] = char_rect(ui, rope, horizontal_offset, point);
+    let [window_x, window_y] = ui.window_pos();
+    ui.get_window_draw_list()
+        .add_rect(
+            [window_x + left, window_y + top],
+            [window_x + right, window_y + bottom],
+            color,
+        )
+        .build();
+}
+
+/// The text position under `pos`, which is relative to the window
+pub fn point_at(ui: &imgui::Ui, rope: &RopeSlice, horizontal_offset: f32, pos: [f32; 2]) -> Point {
+    let line_height = ui.text_line_height_with_spacing();
+    let row = ((pos[1] + ui.scroll_y()) / line_height).max(0.) as usize;
+    // The first line of the window is left empty
+    let row = row
+        .saturating_sub(1)
+        .min(rope.len_lines().saturating_sub(1));
+    let line = line_text(rope, row);
+    let x = pos[0] - horizontal_offset - TEXT_PADDING;
+    let len = line.chars().count();
+    // Closest boundary between characters
+    let col = (0..len)
+        .find(|col| {
+            let left = column_x(ui, &line, *col);
+            let right = column_x(ui, &line, col + 1);
+            x < (left + right) / 2.
+        })
+        .unwrap_or(len);
+    Point {
+        x: col as u16,
+        y: row as u16,
+    }
+}
+
+#[derive(Clone)]
 pub struct Cursor {
     position: Point,
     saved_x: u16,
+    /// The other end of the selection, the cursor itself is always one end
+    anchor: Option<Point>,
 }
 
 impl Cursor {
@@ -14,6 +92,7 @@ impl Cursor {
         Cursor {
             position: Point::default(),
             saved_x: 0,
+            anchor: None,
         }
     }
 
@@ -22,6 +101,7 @@ impl Cursor {
     }
 
     pub fn step(&mut self, direction: Direction, rope: &RopeSlice) {
+        self.anchor = None;
         self.position.step(direction, rope);
         match direction {
             Direction::Left | Direction::Right => {
@@ -36,19 +116,96 @@ impl Cursor {
         }
     }
 
-    pub fn render(&self, ui: &imgui::Ui, horizontal_offset: f32, rope: &RopeSlice) {
+    /// Moves straight to `point`, dropping any selection
+    pub fn set_position(&mut self, point: Point, rope: &RopeSlice) {
+        self.anchor = None;
+        self.position = clamp(point, rope);
+        self.saved_x = self.position.x;
+    }
+
+    /// Moves to `point`, selecting from wherever the cursor was
+    pub fn extend_selection(&mut self, point: Point, rope: &RopeSlice) {
+        let anchor = self.anchor.unwrap_or(self.position);
+        self.set_position(point, rope);
+        self.anchor = Some(anchor);
+    }
+
+    pub fn select(&mut self, anchor: Point, position: Point, rope: &RopeSlice) {
+        self.set_position(position, rope);
+        self.anchor = Some(clamp(anchor, rope));
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.anchor = None;
+    }
+
+    /// Selected characters, including the ones under both ends
+    pub fn selection(&self, rope: &RopeSlice) -> Option<Range<usize>> {
+        self.anchor.map(|anchor| {
+            let anchor = clamp(anchor, rope).index(rope);
+            let position = self.clamped(rope).index(rope);
+            anchor.min(position)..(anchor.max(position) + 1).min(rope.len_chars())
+        })
+    }
+
+    /// Another view on the same buffer may have removed the text under the cursor
+    pub fn clamped(&self, rope: &RopeSlice) -> Point {
+        clamp(self.position, rope)
+    }
+
+    fn render_selection(&self, ui: &imgui::Ui, horizontal_offset: f32, rope: &RopeSlice) {
+        let selection = match self.selection(rope) {
+            Some(selection) if !selection.is_empty() => selection,
+            _ => return,
+        };
         let line_height = ui.text_line_height_with_spacing();
-        use std::borrow::Cow;
-        let line_text: Cow<str> = rope.line(self.position.y as usize).into();
-        let im_str = imgui::ImString::new(line_text[0..self.position.x as usize].to_owned());
-        let left = ui.calc_text_size(&im_str, false, 0.)[0] + horizontal_offset + 8.;
-        let top = (self.position.y + 1) as f32 * line_height - ui.scroll_y();
-        let bottom = top + line_height;
-        let right = left + 7.;
+        let [window_x, window_y] = ui.window_pos();
+        let start = Point::from_index(selection.start, rope);
+        let end = Point::from_index(selection.end, rope);
+        let draw_list = ui.get_window_draw_list();
+        for row in start.y..=end.y {
+            let line = line_text(rope, row as usize);
+            let from = if row == start.y { start.x as usize } else { 0 };
+            let left = column_x(ui, &line, from);
+            let right = if row == end.y {
+                column_x(ui, &line, end.x as usize)
+            } else {
+                // Leave a bit showing for the line break
+                column_x(ui, &line, line.chars().count()) + 7.
+            };
+            let top = (row + 1) as f32 * line_height - ui.scroll_y();
+            let x = window_x + horizontal_offset + TEXT_PADDING;
+            draw_list
+                .add_rect(
+                    [x + left, window_y + top],
+                    [x + right, window_y + top + line_height],
+                    SELECTION_COLOR,
+                )
+                .filled(true)
+                .build();
+        }
+    }
+
+    pub fn render(&self, ui: &imgui::Ui, horizontal_offset: f32, rope: &RopeSlice, focused: bool) {
+        self.render_selection(ui, horizontal_offset, rope);
+        let [left, top, right, bottom] = char_rect(ui, rope, horizontal_offset, self.clamped(rope));
+        let [window_x, window_y] = ui.window_pos();
+        let color = if focused {
+            [1., 1., 1., 0.2]
+        } else {
+            [1., 1., 1., 0.08]
+        };
         ui.get_window_draw_list()
-            .add_rect([left, top], [right, bottom], [1., 1., 1., 0.2])
+            .add_rect(
+                [window_x + left, window_y + top],
+                [window_x + right, window_y + bottom],
+                color,
+            )
             .filled(true)
             .build();
+        if !focused {
+            return;
+        }
         let window_height = ui.window_size()[1];
         if bottom > window_height {
             ui.set_scroll_from_pos_y_with_ratio(bottom + 5., 1.);
@@ -61,8 +218,24 @@ impl Cursor {
     pub fn row(&self) -> usize {
         self.position.y as usize
     }
-    pub fn jump(&mut self, jump_type: JumpType, rope: &RopeSlice, line_count: usize) {
-        self.position.jump(jump_type, rope, line_count);
+    pub fn col(&self) -> usize {
+        self.position.x as usize
+    }
+    pub fn jump(&mut self, jump_type: JumpType, rope: &RopeSlice, line_count: usize, count: usize) {
+        self.anchor = None;
+        self.position.jump(jump_type, rope, line_count, count);
         self.saved_x = self.position.x;
     }
 }
+
+fn clamp(mut point: Point, rope: &RopeSlice) -> Point {
+    let last_line = rope.len_lines().saturating_sub(1) as u16;
+    if point.y > last_line {
+        point.y = last_line;
+    }
+    let line_len = rope.line(point.y as usize).len_chars() as u16;
+    if point.x > line_len {
+        point.x = line_len;
+    }
+    point
+}
@@ -10,6 +10,34 @@ pub enum Error {
     BuildingSyntax,
     #[error("File Extension not supported")]
     UnknownSyntax,
+    #[error("Invalid argument: {0}")]
+    InvalidArg(String),
+    #[error("Unknown option: {0}")]
+    UnknownOption(String),
+    #[error("Invalid keys: {0}")]
+    InvalidKeys(String),
+    #[error("Unknown mode: {0}")]
+    UnknownMode(String),
+    #[error("Unknown status line segment: {0}")]
+    UnknownSegment(String),
+    #[error("Cannot close the last view")]
+    LastView,
+    #[error("Cannot close the last tab")]
+    LastTab,
+    #[error("Invalid mark: {0}")]
+    InvalidMark(char),
+    #[error("Mark not set: {0}")]
+    MarkNotSet(char),
+    #[error("No more items")]
+    NoMoreItems,
+    #[error("Unknown theme: {0}")]
+    UnknownTheme(String),
+    #[error("{0} changed on disk: :e! reloads it, :w! overwrites it")]
+    ChangedOnDisk(String),
+    #[error("No swap file for this buffer")]
+    NoSwapFile,
+    #[error("Clipboard: {0}")]
+    Clipboard(String),
     // #[error("Something went wrong highlighting")]
     // Highlighting,
 }
@@ -0,0 +1,156 @@
+use crate::msg::Msg;
+
+use ignore::WalkBuilder;
+use winit::event_loop::EventLoopProxy;
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Paths sent per message, so a big tree doesn't flood the event loop
+const BATCH_SIZE: usize = 512;
+
+#[derive(Debug, PartialEq)]
+pub enum IndexMsg {
+    /// Walk generation and the files it found
+    Found(u64, Vec<PathBuf>),
+    /// The walk is over, anything it didn't find is gone
+    Done(u64),
+}
+
+/// Files under the working directory. Kept between Skim sessions and refreshed
+/// by a walk on a background thread that streams its results back as `Msg`s.
+#[derive(Default)]
+pub struct FileIndex {
+    files: Vec<PathBuf>,
+    known: HashSet<PathBuf>,
+    /// What the current walk has found so far
+    seen: HashSet<PathBuf>,
+    generation: u64,
+    /// Set to stop the walk that is running
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl FileIndex {
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Starts a new walk, stopping any that is still going. `hidden` includes dotfiles.
+    pub fn refresh(&mut self, msg_sender: EventLoopProxy<Msg>, hidden: bool) {
+        if let Some(cancel) = self.cancel.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel = Some(cancel.clone());
+        self.generation += 1;
+        self.seen.clear();
+        let generation = self.generation;
+        std::thread::spawn(move || walk(generation, hidden, &cancel, &msg_sender));
+    }
+
+    /// True if the list of files changed
+    pub fn update(&mut self, msg: IndexMsg) -> bool {
+        match msg {
+            IndexMsg::Found(generation, paths) if generation == self.generation => {
+                let mut changed = false;
+                for path in paths {
+                    if self.known.insert(path.clone()) {
+                        self.files.push(path.clone());
+                        changed = true;
+                    }
+                    self.seen.insert(path);
+                }
+                changed
+            }
+            IndexMsg::Done(generation) if generation == self.generation => {
+                self.cancel = None;
+                let before = self.files.len();
+                let seen = &self.seen;
+                self.files.retain(|path| seen.contains(path));
+                self.known = std::mem::take(&mut self.seen);
+                self.files.len() != before
+            }
+            // From a walk that was replaced
+            _ => false,
+        }
+    }
+}
+
+/// Respects `.gitignore`, `.ignore` and global git excludes
+fn walk(generation: u64, hidden: bool, cancel: &AtomicBool, msg_sender: &EventLoopProxy<Msg>) {
+    let send = |msg| msg_sender.send_event(Msg::FileIndex(msg)).is_ok();
+    let walker = WalkBuilder::new("./")
+        .hidden(!hidden)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        if entry
+            .file_type()
+            .map_or(false, |file_type| file_type.is_file())
+        {
+            batch.push(entry.into_path());
+        }
+        if batch.len() == BATCH_SIZE {
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+            // The event loop is gone, nobody is listening anymore
+            if !send(IndexMsg::Found(generation, full)) {
+                return;
+            }
+        }
+    }
+    if send(IndexMsg::Found(generation, batch)) {
+        send(IndexMsg::Done(generation));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn walk_results_are_merged() {
+        let mut index = FileIndex::default();
+        index.generation = 1;
+        assert!(index.update(IndexMsg::Found(1, paths(&["a", "b"]))));
+        assert!(!index.update(IndexMsg::Found(1, paths(&["a"]))));
+        assert!(!index.update(IndexMsg::Done(1)));
+        assert_eq!(index.files(), &paths(&["a", "b"])[..]);
+    }
+
+    #[test]
+    fn next_walk_drops_deleted_files() {
+        let mut index = FileIndex::default();
+        index.generation = 1;
+        index.update(IndexMsg::Found(1, paths(&["a", "b"])));
+        index.update(IndexMsg::Done(1));
+        index.generation = 2;
+        // Known files stay listed while the walk is still going
+        assert!(index.update(IndexMsg::Found(2, paths(&["b", "c"]))));
+        assert_eq!(index.files(), &paths(&["a", "b", "c"])[..]);
+        assert!(index.update(IndexMsg::Done(2)));
+        assert_eq!(index.files(), &paths(&["b", "c"])[..]);
+    }
+
+    #[test]
+    fn replaced_walks_are_ignored() {
+        let mut index = FileIndex::default();
+        index.generation = 2;
+        assert!(!index.update(IndexMsg::Found(1, paths(&["a"]))));
+        assert!(!index.update(IndexMsg::Done(1)));
+        assert!(index.files().is_empty());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::msg::Msg;
+
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+use winit::event_loop::EventLoopProxy;
+
+use std::{
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Matches sent per message
+const BATCH_SIZE: usize = 64;
+/// Stops searching past this, nobody is scrolling through more
+const MAX_MATCHES: usize = 10_000;
+
+/// A line that matched
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrepMatch {
+    pub path: PathBuf,
+    /// Counted from 1
+    pub line: usize,
+    /// Counted from 1, in chars
+    pub column: usize,
+    /// The line without its indentation
+    pub text: String,
+    /// Chars of `text` that matched
+    pub matched: Range<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GrepMsg {
+    /// Search generation and what it found
+    Found(u64, Vec<GrepMatch>),
+}
+
+/// Lowercase queries ignore case, like vim's `smartcase`. Anything that isn't a
+/// valid regex is searched for as is.
+fn build_regex(query: &str) -> Option<Regex> {
+    let ignore_case = !query.chars().any(char::is_uppercase);
+    RegexBuilder::new(query)
+        .case_insensitive(ignore_case)
+        .build()
+        .or_else(|_| {
+            RegexBuilder::new(&regex::escape(query))
+                .case_insensitive(ignore_case)
+                .build()
+        })
+        .ok()
+}
+
+/// Searches file contents under the working directory on a background thread,
+/// starting over whenever the query changes
+#[derive(Default)]
+pub struct Grep {
+    matches: Vec<GrepMatch>,
+    generation: u64,
+    /// Set to stop the search that is running
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Grep {
+    pub fn matches(&self) -> &[GrepMatch] {
+        &self.matches
+    }
+
+    /// Drops the old results and stops the old search. `hidden` includes dotfiles.
+    pub fn start(&mut self, query: &str, hidden: bool, msg_sender: EventLoopProxy<Msg>) {
+        self.stop();
+        self.matches.clear();
+        self.generation += 1;
+        let regex = match build_regex(query) {
+            Some(regex) if !query.is_empty() => regex,
+            _ => return,
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel = Some(cancel.clone());
+        let generation = self.generation;
+        std::thread::spawn(move || search(generation, &regex, hidden, &cancel, &msg_sender));
+    }
+
+    /// True if there are new results
+    pub fn update(&mut self, msg: GrepMsg) -> bool {
+        match msg {
+            GrepMsg::Found(generation, matches) if generation == self.generation => {
+                self.matches.extend(matches);
+                true
+            }
+            // From a query that was replaced
+            GrepMsg::Found(..) => false,
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Grep {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// First match on each line of `contents`
+fn search_file(path: &PathBuf, contents: &str, regex: &Regex) -> Vec<GrepMatch> {
+    let mut matches = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if let Some(found) = regex.find(line) {
+            let indent = line.len() - line.trim_start().len();
+            let text = line.trim();
+            // Whitespace that got trimmed off can match too, that part just isn't shown
+            let clip = |byte: usize| (byte.max(indent) - indent).min(text.len());
+            let (start, end) = (clip(found.start()), clip(found.end()));
+            let start_char = text[..start].chars().count();
+            matches.push(GrepMatch {
+                path: path.clone(),
+                line: index + 1,
+                column: line[..found.start()].chars().count() + 1,
+                text: text.to_owned(),
+                matched: start_char..start_char + text[start..end].chars().count(),
+            });
+        }
+    }
+    matches
+}
+
+/// Respects `.gitignore`, `.ignore` and global git excludes, files that aren't
+/// UTF-8 are skipped
+fn search(
+    generation: u64,
+    regex: &Regex,
+    hidden: bool,
+    cancel: &AtomicBool,
+    msg_sender: &EventLoopProxy<Msg>,
+) {
+    let send = |batch| {
+        msg_sender
+            .send_event(Msg::Grep(GrepMsg::Found(generation, batch)))
+            .is_ok()
+    };
+    let walker = WalkBuilder::new("./")
+        .hidden(!hidden)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut found = 0;
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        if !entry
+            .file_type()
+            .map_or(false, |file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let contents = match std::fs::read_to_string(entry.path()) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        let path = entry.path();
+        let path = path.strip_prefix("./").unwrap_or(path).to_path_buf();
+        for grep_match in search_file(&path, &contents, regex) {
+            batch.push(grep_match);
+            found += 1;
+            if batch.len() == BATCH_SIZE || found == MAX_MATCHES {
+                let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                // Stops once the event loop is gone too
+                if !send(full) || found == MAX_MATCHES {
+                    return;
+                }
+            }
+        }
+    }
+    send(batch);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_skip_indentation() {
+        let path = PathBuf::from("test.rs");
+        let regex = build_regex("ümlaut").unwrap();
+        let matches = search_file(&path, "fn main() {\n    let x = ümlaut;\n}\n", &regex);
+        assert_eq!(
+            matches,
+            vec![GrepMatch {
+                path,
+                line: 2,
+                column: 13,
+                text: "let x = ümlaut;".to_owned(),
+                matched: 8..14,
+            }]
+        );
+    }
+
+    #[test]
+    fn match_in_indentation_is_clipped() {
+        let path = PathBuf::from("test.rs");
+        let regex = build_regex(r"\s+x").unwrap();
+        let matches = search_file(&path, "  x", &regex);
+        assert_eq!(matches[0].column, 1);
+        assert_eq!(matches[0].matched, 0..1);
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(build_regex("main").unwrap().is_match("MAIN"));
+        assert!(!build_regex("Main").unwrap().is_match("main"));
+    }
+
+    #[test]
+    fn broken_regex_is_searched_as_is() {
+        assert!(build_regex("foo(").unwrap().is_match("foo(bar)"));
+    }
+}
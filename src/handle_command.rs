@@ -1,26 +1,56 @@
 use crate::{
-    buffer::Buffer,
+    buffer::{Buffer, BufferKey},
+    error::Error,
+    messages::Severity,
     mode::Mode,
-    msg::{Cmd, Msg},
+    msg::{
+        CharSearch, Cmd, DiskAction, JumpType, MouseAction, Msg, PickerKind, SplitDirection,
+        SwapAction,
+    },
+    picker::{
+        AnyPicker, BufferSource, CommandSource, GrepSource, LineSource, MarkSource, Picker,
+        QuickfixSource, RecentSource, RegisterSource, ThemeSource,
+    },
+    point::Point,
+    registers::UNNAMED,
     state::State,
+    swap::swap_path,
+    view::Jump,
 };
 use anyhow::Result;
+use ropey::Rope;
 use winit::event_loop::EventLoopProxy;
 
+use std::path::Path;
+
 pub fn handle_command(
     state: &mut State,
     cmd: Cmd,
     msg_sender: EventLoopProxy<Msg>,
 ) -> Result<bool> {
+    // Commands that make sense more than once take the count, the rest ignore it
+    let (count, cmd) = match cmd {
+        Cmd::Repeat(count, cmd) => (Some(count), *cmd),
+        cmd => (None, cmd),
+    };
+    let times = count.unwrap_or(1);
     Ok(match (state.mode, cmd) {
-        (_, Cmd::SetStatusText(_text)) => {
+        (_, Cmd::ShowMessage(..)) => {
             unreachable!();
         }
+        (_, Cmd::ShowMessages) => {
+            state.messages.show_history();
+            true
+        }
+        (_, Cmd::DismissMessages) => state.messages.dismiss(),
         (_, Cmd::ChangeMode(mode)) => {
             flame::start("change_mode");
             state.mode = mode;
             match mode {
-                Mode::Skim => state.skim_buffer.refresh_files(),
+                Mode::Skim => {
+                    let hidden = state.settings.hidden_files;
+                    state.pickers.open_files(msg_sender, hidden, &state.history)
+                }
                 Mode::Command => state.command_buffer.clear(),
                 _ => {} // the rest don't need setup
             }
@@ -31,53 +61,642 @@ pub fn handle_command(
             unreachable!();
         }
         (_, Cmd::LoadFile(file)) => {
+            let canonical = std::fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+            // Already open, so it shouldn't end up in two buffers
+            if let Some(buffer_key) = state.find_buffer(&canonical) {
+                state.remember_file(canonical);
+                return show_buffer(state, buffer_key);
+            }
             flame::start("load_file");
             let buffer = Buffer::load_file(file, &state.config)?;
+            if swap_path(&canonical).map_or(false, |path| path.exists()) {
+                state.messages.pin(
+                    Severity::Warn,
+                    format!(
+                        "{} has unsaved changes from a crash: :recover, :diffswap or :deleteswap",
+                        buffer.name()
+                    ),
+                );
+            }
+            state.remember_file(canonical);
             let new_buffer_key = state.buffer_keys.insert(());
             state.buffers.insert(new_buffer_key, buffer);
-            state.current_buffer = new_buffer_key;
+            switch_buffer(state, new_buffer_key);
+            state
+                .status_line
+                .refresh_branch(state.buffers[new_buffer_key].file());
+            if state.buffers[new_buffer_key].filetype().is_none() {
+                state.messages.push(
+                    Severity::Warn,
+                    format!(
+                        "No syntax highlighting for {}",
+                        state.buffers[new_buffer_key].name()
+                    ),
+                );
+            }
             flame::end("load_file");
             true
         }
         (_, Cmd::WriteBuffer(maybe_path)) => {
             flame::start("write_buffer");
-            let buffer = &mut state.buffers[state.current_buffer];
+            let buffer = &mut state.buffers[state.current_buffer()];
             buffer.write(maybe_path)?;
+            let message = format!("\"{}\" written", buffer.name());
+            state.messages.push(Severity::Info, message);
+            // The file made it to disk either way
+            if let Err(err) = buffer.save_undo() {
+                state.messages.push(
+                    Severity::Warn,
+                    format!("Couldn't save undo history: {}", err),
+                );
+            }
             flame::end("write_buffer");
             true
         }
+        (_, Cmd::SplitView(direction)) => {
+            let new_view = state.views[state.current_view()].clone();
+            let new_view_key = state.views.insert(new_view);
+            state.tab_mut().split(new_view_key, direction);
+            true
+        }
+        (_, Cmd::FocusView(direction)) => state.tab_mut().focus(direction),
+        (_, Cmd::CycleView) => {
+            state.tab_mut().cycle();
+            true
+        }
+        (_, Cmd::CloseView) => {
+            if state.tab().view_count() > 1 {
+                let closed = state.tab_mut().close_current();
+                state.views.remove(closed);
+            } else if state.tabs.len() > 1 {
+                state.close_tab()?;
+            } else {
+                return Err(Error::LastView.anyhow());
+            }
+            true
+        }
+        (_, Cmd::OnlyView) => {
+            for closed in state.tab_mut().only() {
+                state.views.remove(closed);
+            }
+            true
+        }
+        (_, Cmd::ResizeView(direction, percent)) => {
+            let delta = percent as f32 / 100.;
+            let current_view = state.current_view();
+            state
+                .tab_mut()
+                .layout
+                .resize(current_view, direction, delta)
+        }
+        (_, Cmd::RunCommand(command)) => {
+            crate::command::execute(&command, msg_sender)?;
+            false
+        }
+        (_, Cmd::Map(mode, lhs, rhs, remap)) => {
+            state.keymap.map(mode, &lhs, &rhs, remap)?;
+            false
+        }
+        (_, Cmd::SetOption(arg)) => {
+            state.settings.set(&arg)?;
+            true
+        }
+        (_, Cmd::NewTab) => {
+            state.new_tab()?;
+            true
+        }
+        (_, Cmd::CloseTab) => {
+            state.close_tab()?;
+            true
+        }
+        (_, Cmd::NextTab) => {
+            state.current_tab = match count {
+                // Like vim, `3gt` goes to the third tab instead of three tabs over
+                Some(count) => count.saturating_sub(1).min(state.tabs.len() - 1),
+                None => (state.current_tab + 1) % state.tabs.len(),
+            };
+            true
+        }
+        (_, Cmd::PrevTab) => {
+            let back = times % state.tabs.len();
+            state.current_tab = (state.current_tab + state.tabs.len() - back) % state.tabs.len();
+            true
+        }
+        (_, Cmd::Mouse(view_key, action)) => {
+            if !state.tab().layout.contains(view_key) {
+                return Ok(false);
+            }
+            match action {
+                // Scrolling a view doesn't need to focus it
+                MouseAction::Scroll(_) => {}
+                _ => state.tab_mut().current_view = view_key,
+            }
+            let view = &mut state.views[view_key];
+            view.handle_mouse(action, &state.buffers[view.buffer]);
+            true
+        }
 
-        (Mode::Skim, cmd) => state.skim_buffer.handle_command(cmd, msg_sender)?,
+        (_, Cmd::OpenPicker(kind)) => {
+            let picker: Box<dyn AnyPicker> = match kind {
+                PickerKind::Files => {
+                    let hidden = state.settings.hidden_files;
+                    state.pickers.open_files(msg_sender, hidden, &state.history);
+                    state.mode = Mode::Skim;
+                    return Ok(true);
+                }
+                PickerKind::Buffers => Box::new(Picker::new(BufferSource::new(state))),
+                PickerKind::Commands => Box::new(Picker::new(CommandSource)),
+                PickerKind::Lines => Box::new(Picker::new(LineSource::new(state))),
+                PickerKind::Recent => Box::new(Picker::new(RecentSource::new(state))),
+                PickerKind::Registers => Box::new(Picker::new(RegisterSource::new(state))),
+                PickerKind::Marks => Box::new(Picker::new(MarkSource::new(state))),
+                PickerKind::Themes => Box::new(Picker::new(ThemeSource::new(state))),
+                PickerKind::Grep => {
+                    Box::new(Picker::new(GrepSource::new(state.settings.hidden_files)))
+                }
+                PickerKind::Quickfix => Box::new(Picker::new(QuickfixSource::new(state))),
+            };
+            state.pickers.open(picker);
+            state.mode = Mode::Skim;
+            true
+        }
+        (_, Cmd::ShowBuffer(buffer_key)) => show_buffer(state, buffer_key)?,
+        (_, Cmd::SetQuickfix(locations)) => {
+            let message = format!("{} entries in the quickfix list", locations.len());
+            state.quickfix.set(locations);
+            state.messages.push(Severity::Info, message);
+            true
+        }
+        (_, Cmd::NextQuickfix) => {
+            let location = state
+                .quickfix
+                .next(times)
+                .ok_or_else(|| Error::NoMoreItems.anyhow())?;
+            open_location(location.cmds(), msg_sender);
+            false
+        }
+        (_, Cmd::PrevQuickfix) => {
+            let location = state
+                .quickfix
+                .prev(times)
+                .ok_or_else(|| Error::NoMoreItems.anyhow())?;
+            open_location(location.cmds(), msg_sender);
+            false
+        }
+        (_, Cmd::GoToQuickfix(index)) => {
+            let location = state
+                .quickfix
+                .select(index)
+                .ok_or_else(|| Error::NoMoreItems.anyhow())?;
+            open_location(location.cmds(), msg_sender);
+            false
+        }
+        (_, Cmd::Swap(action)) => {
+            let path = state.buffers[state.current_buffer()]
+                .file()
+                .map(|file| std::fs::canonicalize(file).unwrap_or_else(|_| file.into()))
+                .and_then(|file| swap_path(&file))
+                .filter(|path| path.exists())
+                .ok_or_else(|| Error::NoSwapFile.anyhow())?;
+            state.messages.unpin();
+            match action {
+                SwapAction::Recover => {
+                    let rope = Rope::from_reader(std::fs::File::open(&path)?)?;
+                    let current_view = state.current_view();
+                    let view = &mut state.views[current_view];
+                    let buffer = &mut state.buffers[view.buffer];
+                    buffer.recover(rope, &state.config);
+                    let position = view.cursor.clamped(&buffer.slice());
+                    view.cursor.set_position(position, &buffer.slice());
+                    let message = format!("Recovered {}, :w to keep it", buffer.name());
+                    state.messages.push(Severity::Info, message);
+                }
+                SwapAction::Diff => split_scratch(state, &path)?,
+                SwapAction::Delete => std::fs::remove_file(path)?,
+            }
+            true
+        }
+        (_, Cmd::CheckDisk) => {
+            let changed: Vec<BufferKey> = state
+                .buffers
+                .iter()
+                .filter(|(_, buffer)| buffer.changed_on_disk())
+                .map(|(key, _)| key)
+                .collect();
+            let mut told = false;
+            for &key in changed.iter() {
+                let buffer = &mut state.buffers[key];
+                if buffer.is_modified() {
+                    // Every focus would nag about the same change otherwise
+                    if !buffer.first_sight_of_disk_change() {
+                        continue;
+                    }
+                    let message = format!(
+                        "{} changed on disk: :e! reloads, :keep keeps yours, :diffdisk compares",
+                        buffer.name()
+                    );
+                    state.messages.push(Severity::Warn, message);
+                } else {
+                    // One unreadable file shouldn't keep the rest from reloading
+                    if let Err(err) = buffer.reload(&state.config) {
+                        if buffer.first_sight_of_disk_change() {
+                            let message = format!("Couldn't reload {}: {}", buffer.name(), err);
+                            state.messages.push(Severity::Warn, message);
+                            told = true;
+                        }
+                        continue;
+                    }
+                    clamp_cursors(state, key);
+                    let name = state.buffers[key].name();
+                    state.messages.push(
+                        Severity::Info,
+                        format!("{} changed on disk, reloaded", name),
+                    );
+                }
+                told = true;
+            }
+            told
+        }
+        (_, Cmd::Disk(action)) => {
+            let key = state.current_buffer();
+            let buffer = &mut state.buffers[key];
+            match action {
+                DiskAction::Reload => {
+                    buffer.reload(&state.config)?;
+                    clamp_cursors(state, key);
+                }
+                DiskAction::Keep => buffer.keep_changes(),
+                DiskAction::Diff => {
+                    let file = buffer
+                        .file()
+                        .ok_or_else(|| Error::NeedFilePath.anyhow())?
+                        .to_path_buf();
+                    split_scratch(state, &file)?;
+                }
+            }
+            true
+        }
+        (_, Cmd::SetTheme(name)) => {
+            flame::start("set_theme");
+            let theme = state
+                .config
+                .theme_set
+                .themes
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| Error::UnknownTheme(name).anyhow())?;
+            state.messages.set_theme(&theme);
+            state.config.theme = theme;
+            for buffer in state.buffers.values_mut() {
+                buffer.rehighlight(&state.config);
+            }
+            flame::end("set_theme");
+            true
+        }
+
+        // The clipboard goes into the query or command line as one piece of text
+        (Mode::Skim, Cmd::Paste(register, _)) | (Mode::Command, Cmd::Paste(register, _)) => {
+            let text = state.registers.get(register)?;
+            msg_sender
+                .send_event(Msg::Cmd(Cmd::InsertText(text)))
+                .expect("sending pasted text");
+            false
+        }
+        (Mode::Skim, cmd) => state
+            .pickers
+            .current_mut()
+            .handle_command(cmd, msg_sender)?,
         (Mode::Command, cmd) => state.command_buffer.handle_command(cmd, msg_sender)?,
-        (_, Cmd::Submit) => false, // None of the other modes care
+        // None of the other modes care
+        (_, Cmd::Submit)
+        | (_, Cmd::SubmitIn(_))
+        | (_, Cmd::TogglePicked)
+        | (_, Cmd::ExportQuickfix) => false,
+        (_, Cmd::Yank(register)) => {
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let text = state.buffers[view.buffer].yank_text(&view.cursor);
+            view.cursor.clear_selection();
+            state.registers.set(register, text)?;
+            true
+        }
+        (Mode::Insert, Cmd::Paste(register, _)) => {
+            let text = state.registers.get(register)?;
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            buffer.insert_str(&mut view.cursor, &state.config, &text);
+            true
+        }
+        (_, Cmd::Paste(register, after)) => {
+            let text = state.registers.get(register)?;
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            buffer.paste(&mut view.cursor, &state.config, &text.repeat(times), after);
+            true
+        }
         // All other modes just work on the buffer
         (_, Cmd::Jump(jump_type)) => {
+            let jump_type = match resolve_find(state, jump_type) {
+                Some(jump_type) => jump_type,
+                None => return Ok(false),
+            };
             flame::start("jump");
-            let buffer = &mut state.buffers[state.current_buffer];
-            buffer.jump(jump_type, state.line_count);
+            let jump_type = match (jump_type, count) {
+                // `5gg` and `5G` go to line 5
+                (JumpType::StartOfFile, Some(line)) | (JumpType::EndOfFile, Some(line)) => {
+                    JumpType::Line(line)
+                }
+                (jump_type, _) => jump_type,
+            };
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &state.buffers[view.buffer];
+            if is_big_jump(&jump_type) {
+                view.push_jump(buffer);
+            }
+            buffer.jump(&mut view.cursor, jump_type, view.line_count, times);
             flame::end("jump");
             true
         }
+        (_, Cmd::SetMark(name)) => {
+            let buffer_key = state.current_buffer();
+            if name.is_ascii_uppercase() {
+                match state.file_marks.insert(name, buffer_key) {
+                    Some(old) if old != buffer_key => {
+                        if let Some(old_buffer) = state.buffers.get_mut(old) {
+                            old_buffer.remove_mark(name);
+                        }
+                    }
+                    _ => {}
+                }
+            } else if !name.is_ascii_lowercase() {
+                return Err(Error::InvalidMark(name).anyhow());
+            }
+            let view = &state.views[state.current_view()];
+            state.buffers[buffer_key].set_mark(name, &view.cursor);
+            false
+        }
+        (_, Cmd::JumpToMark(name, exact)) => {
+            let buffer_key = match name {
+                'A'..='Z' => *state
+                    .file_marks
+                    .get(&name)
+                    .ok_or_else(|| Error::MarkNotSet(name).anyhow())?,
+                'a'..='z' | '.' => state.current_buffer(),
+                _ => return Err(Error::InvalidMark(name).anyhow()),
+            };
+            let position = state
+                .buffers
+                .get(buffer_key)
+                .and_then(|buffer| buffer.mark(name))
+                .ok_or_else(|| Error::MarkNotSet(name).anyhow())?;
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            view.push_jump(&state.buffers[view.buffer]);
+            go_to(state, buffer_key, position);
+            if !exact {
+                let view = &mut state.views[current_view];
+                state.buffers[buffer_key].jump(&mut view.cursor, JumpType::StartOfLine, 0, 1);
+            }
+            true
+        }
+        (_, Cmd::OlderJump) => {
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            match view.older_jump(&state.buffers[view.buffer], times) {
+                Some(Jump { buffer, position }) => go_to(state, buffer, position),
+                None => false,
+            }
+        }
+        (_, Cmd::NewerJump) => {
+            let current_view = state.current_view();
+            match state.views[current_view].newer_jump(times) {
+                Some(Jump { buffer, position }) => go_to(state, buffer, position),
+                None => false,
+            }
+        }
+        (_, Cmd::OlderChange) => {
+            let buffer_key = state.current_buffer();
+            match state.buffers[buffer_key].older_change(times) {
+                Some(position) => go_to(state, buffer_key, position),
+                None => false,
+            }
+        }
+        (_, Cmd::NewerChange) => {
+            let buffer_key = state.current_buffer();
+            match state.buffers[buffer_key].newer_change(times) {
+                Some(position) => go_to(state, buffer_key, position),
+                None => false,
+            }
+        }
+        (_, Cmd::Undo) => {
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            buffer.undo(&mut view.cursor, &state.config, times)
+        }
+        (_, Cmd::Redo) => {
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            buffer.redo(&mut view.cursor, &state.config, times)
+        }
         (_, Cmd::InsertChar(c, should_step)) => {
             flame::start("insert");
-            let buffer = &mut state.buffers[state.current_buffer];
-            buffer.insert_char(&state.config, c, should_step);
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            buffer.insert_char(&mut view.cursor, &state.config, c, should_step);
             flame::end("insert");
             true
         }
+        (_, Cmd::InsertText(text)) => {
+            flame::start("insert_text");
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            buffer.insert_str(&mut view.cursor, &state.config, &text);
+            flame::end("insert_text");
+            true
+        }
         (_, Cmd::DeleteChar(direction)) => {
             flame::start("delete");
-            let buffer = &mut state.buffers[state.current_buffer];
-            buffer.delete_char(&state.config, direction);
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            let join_lines = state.mode == Mode::Insert;
+            buffer.delete_char(
+                &mut view.cursor,
+                &state.config,
+                direction,
+                times,
+                join_lines,
+            );
             flame::end("delete");
             true
         }
         (_, Cmd::MoveCursor(direction)) => {
             flame::start("move");
-            let buffer = &mut state.buffers[state.current_buffer];
-            buffer.step(direction);
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &state.buffers[view.buffer];
+            for _ in 0..times {
+                buffer.step(&mut view.cursor, direction);
+            }
             flame::end("move");
             true
         }
+        (_, Cmd::DeleteLine) => {
+            flame::start("delete_line");
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            let text = buffer.delete_lines(&mut view.cursor, &state.config, times);
+            state.registers.set(UNNAMED, text)?;
+            flame::end("delete_line");
+            true
+        }
+        (_, Cmd::DeleteMotion(jump_type)) => {
+            let jump_type = match resolve_find(state, jump_type) {
+                Some(jump_type) => jump_type,
+                None => return Ok(false),
+            };
+            flame::start("delete_motion");
+            let current_view = state.current_view();
+            let view = &mut state.views[current_view];
+            let buffer = &mut state.buffers[view.buffer];
+            let text = buffer.delete_motion(
+                &mut view.cursor,
+                &state.config,
+                jump_type,
+                view.line_count,
+                times,
+            );
+            if !text.is_empty() {
+                state.registers.set(UNNAMED, text)?;
+            }
+            flame::end("delete_motion");
+            true
+        }
+        (_, Cmd::Repeat(..)) => unreachable!(), // Unwrapped above
     })
 }
+
+/// Motions that leave an entry in the jump list
+fn is_big_jump(jump_type: &JumpType) -> bool {
+    match jump_type {
+        JumpType::StartOfFile | JumpType::EndOfFile | JumpType::Line(_) | JumpType::Position(_) => {
+            true
+        }
+        // The two character sneak is the closest thing to a search for now
+        JumpType::Find(search) => search.multi_line,
+        _ => false,
+    }
+}
+
+/// Shows an already loaded buffer in the current view, where it was left
+fn show_buffer(state: &mut State, buffer_key: BufferKey) -> Result<bool> {
+    let current_view = state.current_view();
+    let view = &mut state.views[current_view];
+    if view.buffer == buffer_key || !state.buffers.contains_key(buffer_key) {
+        return Ok(false);
+    }
+    switch_buffer(state, buffer_key);
+    Ok(true)
+}
+
+/// Shows `buffer_key` in the current view. An empty buffer that nothing was done with,
+/// like the one `:tabnew` starts with, goes away instead of staying around unseen.
+fn switch_buffer(state: &mut State, buffer_key: BufferKey) {
+    let current_view = state.current_view();
+    let old = state.views[current_view].buffer;
+    let unused = state.buffers[old].is_untouched()
+        && state
+            .views
+            .values()
+            .filter(|view| view.buffer == old)
+            .count()
+            == 1;
+    let view = &mut state.views[current_view];
+    if unused {
+        state.buffers.remove(old);
+        state.buffer_keys.remove(old);
+    } else {
+        view.push_jump(&state.buffers[old]);
+    }
+    view.set_buffer(buffer_key);
+}
+
+/// Goes through the event loop, so the file is loaded before the cursor moves
+fn open_location(cmds: Vec<Cmd>, msg_sender: EventLoopProxy<Msg>) {
+    for cmd in cmds {
+        msg_sender
+            .send_event(Msg::Cmd(cmd))
+            .expect("sending open location command");
+    }
+}
+
+/// Opens `path` next to the current view, in a buffer that can't be written back over it
+fn split_scratch(state: &mut State, path: &Path) -> Result<()> {
+    let scratch = Buffer::load_scratch(path, &state.config)?;
+    let scratch_key = state.buffer_keys.insert(());
+    state.buffers.insert(scratch_key, scratch);
+    let mut scratch_view = state.views[state.current_view()].clone();
+    scratch_view.set_buffer(scratch_key);
+    let scratch_view_key = state.views.insert(scratch_view);
+    state
+        .tab_mut()
+        .split(scratch_view_key, SplitDirection::Vertical);
+    clamp_cursors(state, scratch_key);
+    Ok(())
+}
+
+/// Keeps the cursors on `buffer` inside its text after it was swapped out from under them
+fn clamp_cursors(state: &mut State, buffer: BufferKey) {
+    let rope = state.buffers[buffer].slice();
+    for view in state
+        .views
+        .values_mut()
+        .filter(|view| view.buffer == buffer)
+    {
+        let position = view.cursor.clamped(&rope);
+        view.cursor.set_position(position, &rope);
+    }
+}
+
+/// Shows `buffer` in the current view with the cursor at `position`.
+/// False if the buffer is gone.
+fn go_to(state: &mut State, buffer: BufferKey, position: Point) -> bool {
+    if !state.buffers.contains_key(buffer) {
+        return false;
+    }
+    let current_view = state.current_view();
+    let view = &mut state.views[current_view];
+    if view.buffer != buffer {
+        view.set_buffer(buffer);
+    }
+    view.cursor
+        .set_position(position, &state.buffers[buffer].slice());
+    true
+}
+
+/// `;` and `,` run the last f/F/t/T again, any other find becomes the new last one.
+/// `None` if there is nothing to repeat.
+fn resolve_find(state: &mut State, jump_type: JumpType) -> Option<JumpType> {
+    match jump_type {
+        JumpType::RepeatFind(reverse) => state.last_find.clone().map(|search| {
+            JumpType::Find(CharSearch {
+                forward: search.forward != reverse,
+                repeat: true,
+                ..search
+            })
+        }),
+        JumpType::Find(search) => {
+            state.last_find = Some(search.clone());
+            Some(JumpType::Find(search))
+        }
+        jump_type => Some(jump_type),
+    }
+}
@@ -0,0 +1,156 @@
+use crate::config_file::state_dir;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Least used files are forgotten past this
+const MAX_FILES: usize = 1000;
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Visit {
+    count: u32,
+    /// Seconds since the epoch
+    last: u64,
+}
+
+impl Visit {
+    /// Opened often and lately scores highest, same idea as z and zoxide
+    fn frecency(&self, now: u64) -> f64 {
+        let weight = match now.saturating_sub(self.last) {
+            age if age < HOUR => 4.,
+            age if age < DAY => 2.,
+            age if age < WEEK => 0.5,
+            _ => 0.25,
+        };
+        f64::from(self.count) * weight
+    }
+}
+
+/// Files that were opened, across sessions. Saved as `history.json` in the state directory.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct FileHistory {
+    /// Canonical paths
+    files: HashMap<PathBuf, Visit>,
+}
+
+impl FileHistory {
+    fn path() -> Option<PathBuf> {
+        state_dir().map(|dir| dir.join("history.json"))
+    }
+
+    /// Empty when there is no history yet
+    pub fn load() -> Result<FileHistory> {
+        let path = match FileHistory::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(FileHistory::default()),
+        };
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = match FileHistory::path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// `path` has to be canonical
+    pub fn visit(&mut self, path: PathBuf) {
+        let now = now();
+        let visit = self.files.entry(path).or_insert(Visit {
+            count: 0,
+            last: now,
+        });
+        visit.count = visit.count.saturating_add(1);
+        visit.last = now;
+        if self.files.len() > MAX_FILES {
+            let ranked = self.ranked();
+            for forgotten in &ranked[MAX_FILES..] {
+                self.files.remove(forgotten);
+            }
+        }
+    }
+
+    /// Zero for files that were never opened
+    pub fn frecency(&self, path: &Path) -> f64 {
+        let now = now();
+        self.files.get(path).map_or(0., |visit| visit.frecency(now))
+    }
+
+    /// Highest frecency first
+    pub fn ranked(&self) -> Vec<PathBuf> {
+        let now = now();
+        let mut files: Vec<(&PathBuf, &Visit)> = self.files.iter().collect();
+        // Ties go to whichever was opened last
+        files.sort_by(|(_, a), (_, b)| {
+            b.frecency(now)
+                .partial_cmp(&a.frecency(now))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.last.cmp(&a.last))
+        });
+        files.into_iter().map(|(path, _)| path.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(visits: &[(&str, u32, u64)]) -> FileHistory {
+        let files = visits
+            .iter()
+            .map(|&(path, count, last)| (PathBuf::from(path), Visit { count, last }))
+            .collect();
+        FileHistory { files }
+    }
+
+    #[test]
+    fn recent_beats_frequent_long_ago() {
+        let now = now();
+        let history = history(&[("old", 10, now - 30 * DAY), ("new", 1, now)]);
+        assert_eq!(
+            history.ranked(),
+            vec![PathBuf::from("new"), PathBuf::from("old")]
+        );
+    }
+
+    #[test]
+    fn ties_go_to_the_last_opened() {
+        let now = now();
+        let history = history(&[("a", 1, now - 2 * HOUR), ("b", 1, now - 3 * HOUR)]);
+        assert_eq!(
+            history.ranked(),
+            vec![PathBuf::from("a"), PathBuf::from("b")]
+        );
+    }
+
+    #[test]
+    fn visits_count_up() {
+        let mut history = FileHistory::default();
+        history.visit(PathBuf::from("a"));
+        history.visit(PathBuf::from("a"));
+        assert_eq!(history.frecency(Path::new("a")), 8.);
+        assert_eq!(history.frecency(Path::new("b")), 0.);
+    }
+}
@@ -0,0 +1,855 @@
+use super::keys::{parse_keys, KeyEvent};
+use crate::{
+    error::Error,
+    mode::Mode,
+    msg::{
+        CharSearch, Cmd, DeleteDirection, Direction, JumpType, OpenIn, PickerKind, SplitDirection,
+    },
+    registers::UNNAMED,
+};
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Percent of the split that a single Ctrl-W +/-/</> moves the divider
+const RESIZE_STEP: i16 = 5;
+/// Stops `:map a b` + `:map b a` from spinning forever
+const MAX_MAP_DEPTH: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Cmds(Vec<Cmd>),
+    /// Typed as if they came from the keyboard, `remap` decides if user maps apply to them
+    Keys {
+        keys: Vec<KeyEvent>,
+        remap: bool,
+    },
+    /// An ex-command, without the leading `:`
+    Ex(String),
+    /// Reads that many typed characters and runs the command made from them, like `f{char}`
+    ReadChars(usize, fn(String) -> Cmd),
+}
+
+/// Characters being read for an `Action::ReadChars`
+#[derive(Debug, Clone)]
+struct Argument {
+    needed: usize,
+    chars: String,
+    cmd: fn(String) -> Cmd,
+}
+
+/// Where reading keys is up to. Threaded through mappings so the rest of a
+/// mapping is read the same way typed keys would be.
+struct Reader {
+    mode: Mode,
+    argument: Option<Argument>,
+}
+
+/// Key sequences for one mode, one level per key
+#[derive(Debug, Clone, Default)]
+struct ModeMap {
+    action: Option<Action>,
+    children: HashMap<KeyEvent, ModeMap>,
+}
+
+impl ModeMap {
+    fn insert(&mut self, keys: &[KeyEvent], action: Action) {
+        match keys.split_first() {
+            Some((first, rest)) => self
+                .children
+                .entry(*first)
+                .or_default()
+                .insert(rest, action),
+            None => self.action = Some(action),
+        }
+    }
+
+    fn get(&self, keys: &[KeyEvent]) -> Option<&ModeMap> {
+        keys.iter()
+            .try_fold(self, |node, key| node.children.get(key))
+    }
+}
+
+enum Lookup<'a> {
+    Exact(&'a Action),
+    /// Mapped, but a longer mapping starts with the same keys too.
+    /// Runs once `timeoutlen` passes without another key.
+    Ambiguous(&'a Action),
+    /// More keys are needed to know what to do
+    Prefix,
+    None,
+}
+
+fn is_valid_key(c: char) -> bool {
+    c != '\r' && (c.is_alphanumeric() || c.is_whitespace() || c.is_ascii_punctuation())
+}
+
+/// What a key does when nothing is mapped to it
+fn fallback(mode: Mode, key: KeyEvent) -> Vec<Cmd> {
+    match (mode, key.plain_char()) {
+        (Mode::Insert, Some(c)) | (Mode::Command, Some(c)) | (Mode::Skim, Some(c))
+            if is_valid_key(c) =>
+        {
+            vec![Cmd::InsertChar(c, true)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn bind(map: &mut ModeMap, notation: &str, cmds: Vec<Cmd>) {
+    bind_action(map, notation, Action::Cmds(cmds));
+}
+
+fn bind_action(map: &mut ModeMap, notation: &str, action: Action) {
+    let keys = parse_keys(notation, &[]).expect("built-in key notation");
+    map.insert(&keys, action);
+}
+
+fn search(target: String, forward: bool, till: bool, multi_line: bool) -> JumpType {
+    JumpType::Find(CharSearch {
+        target,
+        forward,
+        till,
+        multi_line,
+        repeat: false,
+    })
+}
+
+/// `ReadChars` for a single character always has one
+fn first_char(text: &str) -> char {
+    text.chars().next().unwrap_or(' ')
+}
+
+/// f/F/t/T, then the same as motions for `d`
+fn find_motions() -> Vec<(&'static str, Action)> {
+    vec![
+        (
+            "f",
+            Action::ReadChars(1, |c| Cmd::Jump(search(c, true, false, false))),
+        ),
+        (
+            "F",
+            Action::ReadChars(1, |c| Cmd::Jump(search(c, false, false, false))),
+        ),
+        (
+            "t",
+            Action::ReadChars(1, |c| Cmd::Jump(search(c, true, true, false))),
+        ),
+        (
+            "T",
+            Action::ReadChars(1, |c| Cmd::Jump(search(c, false, true, false))),
+        ),
+        (
+            ";",
+            Action::Cmds(vec![Cmd::Jump(JumpType::RepeatFind(false))]),
+        ),
+        (
+            ",",
+            Action::Cmds(vec![Cmd::Jump(JumpType::RepeatFind(true))]),
+        ),
+        (
+            "df",
+            Action::ReadChars(1, |c| Cmd::DeleteMotion(search(c, true, false, false))),
+        ),
+        (
+            "dF",
+            Action::ReadChars(1, |c| Cmd::DeleteMotion(search(c, false, false, false))),
+        ),
+        (
+            "dt",
+            Action::ReadChars(1, |c| Cmd::DeleteMotion(search(c, true, true, false))),
+        ),
+        (
+            "dT",
+            Action::ReadChars(1, |c| Cmd::DeleteMotion(search(c, false, true, false))),
+        ),
+        (
+            "d;",
+            Action::Cmds(vec![Cmd::DeleteMotion(JumpType::RepeatFind(false))]),
+        ),
+        (
+            "d,",
+            Action::Cmds(vec![Cmd::DeleteMotion(JumpType::RepeatFind(true))]),
+        ),
+        // Two characters anywhere in the file, like vim-sneak
+        (
+            "s",
+            Action::ReadChars(2, |c| Cmd::Jump(search(c, true, false, true))),
+        ),
+        (
+            "S",
+            Action::ReadChars(2, |c| Cmd::Jump(search(c, false, false, true))),
+        ),
+    ]
+}
+
+fn default_maps() -> HashMap<Mode, ModeMap> {
+    let mut maps = HashMap::new();
+
+    let mut shared = ModeMap::default();
+    bind(
+        &mut shared,
+        "<Left>",
+        vec![Cmd::MoveCursor(Direction::Left)],
+    );
+    bind(
+        &mut shared,
+        "<Right>",
+        vec![Cmd::MoveCursor(Direction::Right)],
+    );
+    bind(
+        &mut shared,
+        "<Down>",
+        vec![Cmd::MoveCursor(Direction::Down)],
+    );
+    bind(&mut shared, "<Up>", vec![Cmd::MoveCursor(Direction::Up)]);
+
+    let mut normal = shared.clone();
+    bind(
+        &mut normal,
+        "<C-p>",
+        vec![Cmd::OpenPicker(PickerKind::Files)],
+    );
+    bind(&mut normal, "<C-f>", vec![Cmd::Jump(JumpType::PageForward)]);
+    bind(
+        &mut normal,
+        "<C-b>",
+        vec![Cmd::Jump(JumpType::PageBackward)],
+    );
+    bind(&mut normal, "h", vec![Cmd::MoveCursor(Direction::Left)]);
+    bind(&mut normal, "l", vec![Cmd::MoveCursor(Direction::Right)]);
+    bind(&mut normal, "k", vec![Cmd::MoveCursor(Direction::Up)]);
+    bind(&mut normal, "j", vec![Cmd::MoveCursor(Direction::Down)]);
+    bind(&mut normal, "i", vec![Cmd::ChangeMode(Mode::Insert)]);
+    bind(
+        &mut normal,
+        "I",
+        vec![
+            Cmd::Jump(JumpType::StartOfLine),
+            Cmd::ChangeMode(Mode::Insert),
+        ],
+    );
+    bind(&mut normal, ":", vec![Cmd::ChangeMode(Mode::Command)]);
+    bind(
+        &mut normal,
+        "x",
+        vec![Cmd::DeleteChar(DeleteDirection::After)],
+    );
+    bind(
+        &mut normal,
+        "X",
+        vec![Cmd::DeleteChar(DeleteDirection::Before)],
+    );
+    bind(&mut normal, "dd", vec![Cmd::DeleteLine]);
+    bind(
+        &mut normal,
+        "a",
+        vec![
+            Cmd::MoveCursor(Direction::Right),
+            Cmd::ChangeMode(Mode::Insert),
+        ],
+    );
+    bind(&mut normal, "w", vec![Cmd::Jump(JumpType::NextWord)]);
+    bind(&mut normal, "e", vec![Cmd::Jump(JumpType::EndOfWord)]);
+    bind(&mut normal, "b", vec![Cmd::Jump(JumpType::PrevWord)]);
+    bind(
+        &mut normal,
+        "A",
+        vec![
+            Cmd::Jump(JumpType::EndOfLine),
+            Cmd::ChangeMode(Mode::Insert),
+        ],
+    );
+    let open_line = vec![
+        Cmd::Jump(JumpType::EndOfLine),
+        Cmd::InsertChar('\n', false),
+        Cmd::MoveCursor(Direction::Down),
+        // Reset the saved x value
+        Cmd::MoveCursor(Direction::Left),
+        Cmd::MoveCursor(Direction::Right),
+        Cmd::ChangeMode(Mode::Insert),
+    ];
+    bind(&mut normal, "o", open_line.clone());
+    bind(
+        &mut normal,
+        "O",
+        std::iter::once(Cmd::MoveCursor(Direction::Up))
+            .chain(open_line)
+            .collect(),
+    );
+    bind(
+        &mut normal,
+        ">",
+        vec![
+            Cmd::Jump(JumpType::StartOfLine),
+            Cmd::InsertChar('\t', true),
+        ],
+    );
+    bind(&mut normal, "<Esc>", vec![Cmd::DismissMessages]);
+    bind(&mut normal, "<CR>", vec![Cmd::DismissMessages]);
+    for (notation, cmd) in vec![
+        ("gl", Cmd::Jump(JumpType::EndOfLine)),
+        ("gh", Cmd::Jump(JumpType::StartOfLine)),
+        ("gk", Cmd::Jump(JumpType::StartOfFile)),
+        ("gg", Cmd::Jump(JumpType::StartOfFile)),
+        ("gj", Cmd::Jump(JumpType::EndOfFile)),
+        ("G", Cmd::Jump(JumpType::EndOfFile)),
+        ("gt", Cmd::NextTab),
+        ("gT", Cmd::PrevTab),
+        ("<C-o>", Cmd::OlderJump),
+        ("<C-i>", Cmd::NewerJump),
+        ("g;", Cmd::OlderChange),
+        ("g,", Cmd::NewerChange),
+        ("u", Cmd::Undo),
+        ("<C-r>", Cmd::Redo),
+        ("<C-w>h", Cmd::FocusView(Direction::Left)),
+        ("<C-w>l", Cmd::FocusView(Direction::Right)),
+        ("<C-w>k", Cmd::FocusView(Direction::Up)),
+        ("<C-w>j", Cmd::FocusView(Direction::Down)),
+        ("<C-w>w", Cmd::CycleView),
+        ("<C-w><C-w>", Cmd::CycleView),
+        ("<C-w>s", Cmd::SplitView(SplitDirection::Horizontal)),
+        ("<C-w>v", Cmd::SplitView(SplitDirection::Vertical)),
+        ("<C-w>c", Cmd::CloseView),
+        ("<C-w>q", Cmd::CloseView),
+        ("<C-w>o", Cmd::OnlyView),
+        (
+            "<C-w>+",
+            Cmd::ResizeView(SplitDirection::Horizontal, RESIZE_STEP),
+        ),
+        (
+            "<C-w>-",
+            Cmd::ResizeView(SplitDirection::Horizontal, -RESIZE_STEP),
+        ),
+        (
+            "<C-w>>",
+            Cmd::ResizeView(SplitDirection::Vertical, RESIZE_STEP),
+        ),
+        (
+            "<C-w><lt>",
+            Cmd::ResizeView(SplitDirection::Vertical, -RESIZE_STEP),
+        ),
+    ] {
+        bind(&mut normal, notation, vec![cmd]);
+    }
+    bind(&mut normal, "%", vec![Cmd::Jump(JumpType::MatchingBracket)]);
+    bind(&mut normal, "y", vec![Cmd::Yank(UNNAMED)]);
+    bind(&mut normal, "p", vec![Cmd::Paste(UNNAMED, true)]);
+    bind(&mut normal, "P", vec![Cmd::Paste(UNNAMED, false)]);
+    for register in (b'a'..=b'z')
+        .chain(b'A'..=b'Z')
+        .map(char::from)
+        .chain("+*".chars())
+    {
+        bind(
+            &mut normal,
+            &format!("\"{}y", register),
+            vec![Cmd::Yank(register)],
+        );
+        bind(
+            &mut normal,
+            &format!("\"{}p", register),
+            vec![Cmd::Paste(register, true)],
+        );
+        bind(
+            &mut normal,
+            &format!("\"{}P", register),
+            vec![Cmd::Paste(register, false)],
+        );
+    }
+    for (notation, motion) in vec![
+        ("w", JumpType::NextWord),
+        ("e", JumpType::EndOfWord),
+        ("b", JumpType::PrevWord),
+        ("gl", JumpType::EndOfLine),
+        ("gh", JumpType::StartOfLine),
+        ("%", JumpType::MatchingBracket),
+    ] {
+        bind(
+            &mut normal,
+            &format!("d{}", notation),
+            vec![Cmd::DeleteMotion(motion)],
+        );
+    }
+    bind_action(
+        &mut normal,
+        "m",
+        Action::ReadChars(1, |c| Cmd::SetMark(first_char(&c))),
+    );
+    bind_action(
+        &mut normal,
+        "'",
+        Action::ReadChars(1, |c| Cmd::JumpToMark(first_char(&c), false)),
+    );
+    bind_action(
+        &mut normal,
+        "`",
+        Action::ReadChars(1, |c| Cmd::JumpToMark(first_char(&c), true)),
+    );
+    for (notation, action) in find_motions() {
+        bind_action(&mut normal, notation, action);
+    }
+    maps.insert(Mode::Normal, normal);
+
+    let mut insert = shared.clone();
+    bind(
+        &mut insert,
+        "<BS>",
+        vec![Cmd::DeleteChar(DeleteDirection::Before)],
+    );
+    bind(&mut insert, "<CR>", vec![Cmd::InsertChar('\n', true)]);
+    bind(&mut insert, "<Tab>", vec![Cmd::InsertChar('\t', true)]);
+    bind(&mut insert, "<Esc>", vec![Cmd::ChangeMode(Mode::Normal)]);
+    bind(&mut insert, "<C-S-v>", vec![Cmd::Paste('+', false)]);
+    maps.insert(Mode::Insert, insert);
+
+    let mut command = shared.clone();
+    bind(
+        &mut command,
+        "<BS>",
+        vec![Cmd::DeleteChar(DeleteDirection::Before)],
+    );
+    bind(
+        &mut command,
+        "<CR>",
+        vec![Cmd::Submit, Cmd::ChangeMode(Mode::Normal)],
+    );
+    bind(&mut command, "<Esc>", vec![Cmd::ChangeMode(Mode::Normal)]);
+    bind(&mut command, "<C-S-v>", vec![Cmd::Paste('+', false)]);
+    maps.insert(Mode::Command, command);
+
+    let mut skim = shared;
+    bind(&mut skim, "<C-j>", vec![Cmd::MoveCursor(Direction::Down)]);
+    bind(&mut skim, "<C-k>", vec![Cmd::MoveCursor(Direction::Up)]);
+    bind(
+        &mut skim,
+        "<BS>",
+        vec![Cmd::DeleteChar(DeleteDirection::Before)],
+    );
+    bind(
+        &mut skim,
+        "<PageUp>",
+        vec![Cmd::Jump(JumpType::PageBackward)],
+    );
+    bind(
+        &mut skim,
+        "<PageDown>",
+        vec![Cmd::Jump(JumpType::PageForward)],
+    );
+    bind(&mut skim, "<Tab>", vec![Cmd::TogglePicked]);
+    bind(&mut skim, "<CR>", vec![Cmd::Submit]);
+    bind(
+        &mut skim,
+        "<C-x>",
+        vec![Cmd::SubmitIn(OpenIn::Split(SplitDirection::Horizontal))],
+    );
+    bind(
+        &mut skim,
+        "<C-v>",
+        vec![Cmd::SubmitIn(OpenIn::Split(SplitDirection::Vertical))],
+    );
+    bind(&mut skim, "<C-t>", vec![Cmd::SubmitIn(OpenIn::Tab)]);
+    bind(&mut skim, "<C-q>", vec![Cmd::ExportQuickfix]);
+    bind(&mut skim, "<Esc>", vec![Cmd::ChangeMode(Mode::Normal)]);
+    bind(&mut skim, "<C-S-v>", vec![Cmd::Paste('+', false)]);
+    maps.insert(Mode::Skim, skim);
+
+    maps
+}
+
+/// Per mode maps from key sequences to actions. User maps take priority over the built-in ones.
+pub struct Keymap {
+    defaults: HashMap<Mode, ModeMap>,
+    user: HashMap<Mode, ModeMap>,
+    leader: Vec<KeyEvent>,
+    pending: Vec<KeyEvent>,
+    /// When the last pending key came in
+    pending_since: Option<Instant>,
+    /// Typed before the keys of a normal mode command, like `5j`
+    count: Option<usize>,
+    /// Typed in the middle of them, like `d3w`. Multiplies `count`, same as vim.
+    operator_count: Option<usize>,
+    argument: Option<Argument>,
+}
+
+impl Keymap {
+    pub fn new(leader: Option<&str>) -> Result<Keymap, Error> {
+        Ok(Keymap {
+            defaults: default_maps(),
+            user: HashMap::new(),
+            leader: parse_keys(leader.unwrap_or("\\"), &[])?,
+            pending: Vec::new(),
+            pending_since: None,
+            count: None,
+            operator_count: None,
+            argument: None,
+        })
+    }
+
+    /// Maps `lhs` to `rhs` (both in key notation). A `rhs` starting with `:` and
+    /// without a trailing `<CR>` is run as an ex-command.
+    pub fn map(&mut self, mode: Mode, lhs: &str, rhs: &str, remap: bool) -> Result<(), Error> {
+        let lhs = parse_keys(lhs, &self.leader)?;
+        let action = if rhs.starts_with(':') && !rhs.to_lowercase().ends_with("<cr>") {
+            Action::Ex(rhs[1..].to_owned())
+        } else {
+            Action::Keys {
+                keys: parse_keys(rhs, &self.leader)?,
+                remap,
+            }
+        };
+        self.user.entry(mode).or_default().insert(&lhs, action);
+        Ok(())
+    }
+
+    fn maps(&self, mode: Mode, remap: bool) -> impl Iterator<Item = &ModeMap> {
+        let user = if remap { self.user.get(&mode) } else { None };
+        user.into_iter().chain(self.defaults.get(&mode))
+    }
+
+    fn lookup(&self, mode: Mode, keys: &[KeyEvent], remap: bool) -> Lookup {
+        let nodes = self
+            .maps(mode, remap)
+            .filter_map(|map| map.get(keys))
+            .collect::<Vec<_>>();
+        let exact = nodes.iter().find_map(|node| node.action.as_ref());
+        let longer = nodes.iter().any(|node| !node.children.is_empty());
+        match (exact, longer) {
+            (Some(action), true) => Lookup::Ambiguous(action),
+            (Some(action), false) => Lookup::Exact(action),
+            (None, true) => Lookup::Prefix,
+            (None, false) => Lookup::None,
+        }
+    }
+
+    /// Keys typed so far that are waiting on another key, in key notation
+    pub fn pending_keys(&self) -> String {
+        let count = |count: Option<usize>| count.map(|n| n.to_string()).unwrap_or_default();
+        count(self.count)
+            + &self
+                .pending
+                .iter()
+                .map(|key| key.name())
+                .collect::<String>()
+            + &count(self.operator_count)
+    }
+
+    /// When the pending keys stop waiting for more input
+    pub fn deadline(&self, timeout_len: u64) -> Option<Instant> {
+        self.pending_since
+            .map(|since| since + Duration::from_millis(timeout_len))
+    }
+
+    /// Digits that start or continue a count, `0` on its own is still a key. After
+    /// the first keys of a command, like the `d` of `d3w`, digits that don't
+    /// continue a mapping count the rest of it.
+    fn read_count(&mut self, key: KeyEvent, mode: Mode) -> bool {
+        if mode != Mode::Normal || self.argument.is_some() {
+            return false;
+        }
+        let count = if self.pending.is_empty() {
+            &mut self.count
+        } else {
+            let mut keys = self.pending.clone();
+            keys.push(key);
+            if !matches!(self.lookup(mode, &keys, true), Lookup::None) {
+                return false;
+            }
+            &mut self.operator_count
+        };
+        match key.plain_char().and_then(|c| c.to_digit(10)) {
+            Some(digit) if digit > 0 || count.is_some() => {
+                let so_far = count.unwrap_or(0);
+                *count = Some(so_far.saturating_mul(10).saturating_add(digit as usize));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true if the pending keys changed and need to be redrawn
+    pub fn process(&mut self, key: KeyEvent, mode: Mode, cmd_sender: impl Fn(Cmd)) -> bool {
+        if self.read_count(key, mode) {
+            return true;
+        }
+        let was_pending = !self.pending.is_empty() || self.count.is_some();
+        self.with_reader(mode, cmd_sender, |keymap, pending, reader, cmd_sender| {
+            keymap.feed(key, pending, reader, true, 0, &cmd_sender)
+        });
+        was_pending || !self.pending.is_empty()
+    }
+
+    /// No more keys came in time, so the pending ones run as the longest mapping they match
+    pub fn timeout(&mut self, mode: Mode, cmd_sender: impl Fn(Cmd)) -> bool {
+        let was_pending = !self.pending.is_empty();
+        self.with_reader(mode, cmd_sender, |keymap, pending, reader, cmd_sender| {
+            keymap.resolve(pending, reader, true, 0, &cmd_sender)
+        });
+        was_pending
+    }
+
+    /// The count goes to the first command the keys turn into. It sticks around
+    /// while keys are pending and is dropped if they turn out to do nothing.
+    /// Both counts of `2d3w` make one, like vim's `6dw`.
+    fn with_reader(
+        &mut self,
+        mode: Mode,
+        cmd_sender: impl Fn(Cmd),
+        run: impl FnOnce(&Keymap, &mut Vec<KeyEvent>, &mut Reader, &dyn Fn(Cmd)),
+    ) {
+        let mut pending = std::mem::take(&mut self.pending);
+        let mut reader = Reader {
+            mode,
+            argument: self.argument.take(),
+        };
+        let (typed_count, operator_count) = (self.count.take(), self.operator_count.take());
+        let count = Cell::new(match (typed_count, operator_count) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(1).saturating_mul(b.unwrap_or(1))),
+        });
+        run(self, &mut pending, &mut reader, &|cmd| match count.take() {
+            Some(count) => cmd_sender(Cmd::Repeat(count, Box::new(cmd))),
+            None => cmd_sender(cmd),
+        });
+        self.argument = reader.argument;
+        if (!pending.is_empty() || self.argument.is_some()) && count.take().is_some() {
+            self.count = typed_count;
+            self.operator_count = operator_count;
+        }
+        self.set_pending(pending);
+    }
+
+    fn set_pending(&mut self, pending: Vec<KeyEvent>) {
+        // Like vim, the wait starts over with every key typed
+        self.pending_since = if pending.is_empty() {
+            None
+        } else {
+            Some(Instant::now())
+        };
+        self.pending = pending;
+    }
+
+    fn feed(
+        &self,
+        key: KeyEvent,
+        pending: &mut Vec<KeyEvent>,
+        reader: &mut Reader,
+        remap: bool,
+        depth: usize,
+        cmd_sender: &impl Fn(Cmd),
+    ) {
+        if let Some(mut argument) = reader.argument.take() {
+            // Anything that isn't text, like <Esc>, gives up on it
+            if let Some(c) = key.plain_char() {
+                argument.chars.push(c);
+                if argument.chars.chars().count() < argument.needed {
+                    reader.argument = Some(argument);
+                } else {
+                    run_cmds(vec![(argument.cmd)(argument.chars)], reader, cmd_sender);
+                }
+            }
+            return;
+        }
+        pending.push(key);
+        match self.lookup(reader.mode, pending, remap) {
+            Lookup::Prefix | Lookup::Ambiguous(_) => {}
+            Lookup::Exact(action) => {
+                pending.clear();
+                self.run(action, reader, depth, cmd_sender);
+            }
+            Lookup::None => self.flush_first(pending, reader, remap, depth, cmd_sender),
+        }
+    }
+
+    /// Nothing mapped starts with `pending` so its first key is handled on its own
+    /// and the rest are fed back in
+    fn flush_first(
+        &self,
+        pending: &mut Vec<KeyEvent>,
+        reader: &mut Reader,
+        remap: bool,
+        depth: usize,
+        cmd_sender: &impl Fn(Cmd),
+    ) {
+        let keys = std::mem::take(pending);
+        if let Some((first, rest)) = keys.split_first() {
+            let exact = self
+                .maps(reader.mode, remap)
+                .filter_map(|map| map.get(&keys[..1]))
+                .find_map(|node| node.action.clone());
+            match exact {
+                Some(action) => self.run(&action, reader, depth, cmd_sender),
+                None => run_cmds(fallback(reader.mode, *first), reader, cmd_sender),
+            }
+            for key in rest {
+                self.feed(*key, pending, reader, remap, depth, cmd_sender);
+            }
+        }
+    }
+
+    fn run(&self, action: &Action, reader: &mut Reader, depth: usize, cmd_sender: &impl Fn(Cmd)) {
+        match action {
+            Action::Cmds(cmds) => run_cmds(cmds.clone(), reader, cmd_sender),
+            Action::Ex(command) => cmd_sender(Cmd::RunCommand(command.clone())),
+            Action::ReadChars(needed, cmd) => {
+                reader.argument = Some(Argument {
+                    needed: *needed,
+                    chars: String::new(),
+                    cmd: *cmd,
+                })
+            }
+            Action::Keys { keys, remap } => {
+                if depth >= MAX_MAP_DEPTH {
+                    return;
+                }
+                let mut pending = Vec::new();
+                for key in keys {
+                    self.feed(*key, &mut pending, reader, *remap, depth + 1, cmd_sender);
+                }
+                // Whatever is left can't turn into a longer mapping anymore
+                self.resolve(&mut pending, reader, *remap, depth + 1, cmd_sender);
+            }
+        }
+    }
+
+    fn resolve(
+        &self,
+        pending: &mut Vec<KeyEvent>,
+        reader: &mut Reader,
+        remap: bool,
+        depth: usize,
+        cmd_sender: &impl Fn(Cmd),
+    ) {
+        while !pending.is_empty() {
+            match self.lookup(reader.mode, pending, remap) {
+                Lookup::Exact(action) | Lookup::Ambiguous(action) => {
+                    pending.clear();
+                    self.run(action, reader, depth, cmd_sender);
+                }
+                Lookup::Prefix | Lookup::None => {
+                    self.flush_first(pending, reader, remap, depth, cmd_sender)
+                }
+            }
+        }
+    }
+}
+
+/// Keeps track of mode changes so the rest of a mapping is read in the right mode
+fn run_cmds(cmds: Vec<Cmd>, reader: &mut Reader, cmd_sender: &impl Fn(Cmd)) {
+    for cmd in cmds {
+        if let Cmd::ChangeMode(new_mode) = cmd {
+            reader.mode = new_mode;
+        }
+        cmd_sender(cmd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    /// Types `notation` in `mode` and returns the commands that came out
+    fn type_keys(keymap: &mut Keymap, mode: Mode, notation: &str) -> Vec<Cmd> {
+        let cmds = RefCell::new(Vec::new());
+        for key in parse_keys(notation, &[]).unwrap() {
+            keymap.process(key, mode, |cmd| cmds.borrow_mut().push(cmd));
+        }
+        cmds.into_inner()
+    }
+
+    fn timeout(keymap: &mut Keymap, mode: Mode) -> Vec<Cmd> {
+        let cmds = RefCell::new(Vec::new());
+        keymap.timeout(mode, |cmd| cmds.borrow_mut().push(cmd));
+        cmds.into_inner()
+    }
+
+    #[test]
+    fn sequences_wait_for_their_last_key() {
+        let mut keymap = Keymap::new(None).unwrap();
+        assert_eq!(type_keys(&mut keymap, Mode::Normal, "g"), vec![]);
+        assert_eq!(keymap.pending_keys(), "g");
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Normal, "g"),
+            vec![Cmd::Jump(JumpType::StartOfFile)]
+        );
+        assert_eq!(keymap.pending_keys(), "");
+    }
+
+    #[test]
+    fn count_goes_to_the_command() {
+        let mut keymap = Keymap::new(None).unwrap();
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Normal, "12j"),
+            vec![Cmd::Repeat(12, Box::new(Cmd::MoveCursor(Direction::Down)))]
+        );
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Normal, "j"),
+            vec![Cmd::MoveCursor(Direction::Down)]
+        );
+    }
+
+    #[test]
+    fn count_after_an_operator() {
+        let mut keymap = Keymap::new(None).unwrap();
+        let delete_words =
+            |count| Cmd::Repeat(count, Box::new(Cmd::DeleteMotion(JumpType::NextWord)));
+        assert_eq!(type_keys(&mut keymap, Mode::Normal, "d3"), vec![]);
+        assert_eq!(keymap.pending_keys(), "d3");
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Normal, "w"),
+            vec![delete_words(3)]
+        );
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Normal, "2d3w"),
+            vec![delete_words(6)]
+        );
+    }
+
+    #[test]
+    fn digits_are_text_in_insert_mode() {
+        let mut keymap = Keymap::new(None).unwrap();
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Insert, "1"),
+            vec![Cmd::InsertChar('1', true)]
+        );
+    }
+
+    #[test]
+    fn ambiguous_map_runs_on_timeout() {
+        let mut keymap = Keymap::new(None).unwrap();
+        keymap.map(Mode::Normal, "jk", ":quit", true).unwrap();
+        assert_eq!(type_keys(&mut keymap, Mode::Normal, "j"), vec![]);
+        assert_eq!(
+            timeout(&mut keymap, Mode::Normal),
+            vec![Cmd::MoveCursor(Direction::Down)]
+        );
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Normal, "jk"),
+            vec![Cmd::RunCommand("quit".to_owned())]
+        );
+    }
+
+    #[test]
+    fn noremap_ignores_user_maps() {
+        let mut keymap = Keymap::new(None).unwrap();
+        keymap.map(Mode::Normal, "j", "k", false).unwrap();
+        keymap.map(Mode::Normal, "k", "j", false).unwrap();
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Normal, "j"),
+            vec![Cmd::MoveCursor(Direction::Up)]
+        );
+    }
+
+    #[test]
+    fn recursive_maps_stop() {
+        let mut keymap = Keymap::new(None).unwrap();
+        keymap.map(Mode::Normal, "a", "b", true).unwrap();
+        keymap.map(Mode::Normal, "b", "a", true).unwrap();
+        assert_eq!(type_keys(&mut keymap, Mode::Normal, "a"), vec![]);
+    }
+}
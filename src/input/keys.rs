@@ -0,0 +1,246 @@
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+use crate::error::Error;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    /// Keys that don't produce a character
+    Code(VirtualKeyCode),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state: ModifiersState) -> Modifiers {
+        Modifiers {
+            ctrl: state.ctrl(),
+            alt: state.alt(),
+            shift: state.shift(),
+            logo: state.logo(),
+        }
+    }
+}
+
+impl Modifiers {
+    /// Modifiers that turn a key into a shortcut instead of text
+    fn is_chord(self) -> bool {
+        self.ctrl || self.alt || self.logo
+    }
+}
+
+/// A single key press as far as mappings are concerned
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+const NAMED_CODES: &[(&str, VirtualKeyCode)] = &[
+    ("esc", VirtualKeyCode::Escape),
+    ("cr", VirtualKeyCode::Return),
+    ("enter", VirtualKeyCode::Return),
+    ("return", VirtualKeyCode::Return),
+    ("bs", VirtualKeyCode::Back),
+    ("tab", VirtualKeyCode::Tab),
+    ("del", VirtualKeyCode::Delete),
+    ("left", VirtualKeyCode::Left),
+    ("right", VirtualKeyCode::Right),
+    ("up", VirtualKeyCode::Up),
+    ("down", VirtualKeyCode::Down),
+    ("home", VirtualKeyCode::Home),
+    ("end", VirtualKeyCode::End),
+    ("pageup", VirtualKeyCode::PageUp),
+    ("pagedown", VirtualKeyCode::PageDown),
+    ("insert", VirtualKeyCode::Insert),
+    ("f1", VirtualKeyCode::F1),
+    ("f2", VirtualKeyCode::F2),
+    ("f3", VirtualKeyCode::F3),
+    ("f4", VirtualKeyCode::F4),
+    ("f5", VirtualKeyCode::F5),
+    ("f6", VirtualKeyCode::F6),
+    ("f7", VirtualKeyCode::F7),
+    ("f8", VirtualKeyCode::F8),
+    ("f9", VirtualKeyCode::F9),
+    ("f10", VirtualKeyCode::F10),
+    ("f11", VirtualKeyCode::F11),
+    ("f12", VirtualKeyCode::F12),
+];
+
+const NAMED_CHARS: &[(&str, char)] = &[("space", ' '), ("lt", '<'), ("bar", '|'), ("bslash", '\\')];
+
+/// The character a key code stands for in shortcuts, independent of what the
+/// keyboard layout would type with the modifiers held
+fn code_char(code: VirtualKeyCode) -> Option<char> {
+    use VirtualKeyCode::*;
+    let letters = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    let digits = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    if let Some(index) = letters.iter().position(|letter| *letter == code) {
+        return Some((b'a' + index as u8) as char);
+    }
+    if let Some(index) = digits.iter().position(|digit| *digit == code) {
+        return Some((b'0' + index as u8) as char);
+    }
+    Some(match code {
+        Space => ' ',
+        LBracket => '[',
+        RBracket => ']',
+        Semicolon => ';',
+        Apostrophe => '\'',
+        Comma => ',',
+        Period => '.',
+        Slash => '/',
+        Backslash => '\\',
+        Minus => '-',
+        Equals => '=',
+        Grave => '`',
+        _ => return None,
+    })
+}
+
+impl KeyEvent {
+    pub fn new(key: Key, modifiers: Modifiers) -> KeyEvent {
+        KeyEvent { key, modifiers }
+    }
+
+    /// Text typed without any shortcut modifiers
+    pub fn plain_char(self) -> Option<char> {
+        match self.key {
+            Key::Char(c) if self.modifiers == Modifiers::default() => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Typed text comes in as characters, which already have shift applied.
+    /// Shortcuts come in through `from_code` instead.
+    pub fn from_char(c: char, modifiers: Modifiers) -> Option<KeyEvent> {
+        // Ctrl + Alt together is AltGr on a lot of layouts, which still types text
+        let is_alt_gr = modifiers.ctrl && modifiers.alt;
+        if c.is_control() || (modifiers.is_chord() && !is_alt_gr) {
+            None
+        } else {
+            Some(KeyEvent::new(Key::Char(c), Modifiers::default()))
+        }
+    }
+
+    pub fn from_code(code: VirtualKeyCode, modifiers: Modifiers) -> Option<KeyEvent> {
+        if NAMED_CODES.iter().any(|(_, named)| *named == code) {
+            Some(KeyEvent::new(Key::Code(code), modifiers))
+        } else if modifiers.is_chord() {
+            code_char(code).map(|c| KeyEvent::new(Key::Char(c), modifiers))
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> String {
+        let key_name = match self.key {
+            Key::Char(c) => NAMED_CHARS
+                .iter()
+                .find(|(_, named)| *named == c)
+                .map(|(name, _)| name.to_string()),
+            Key::Code(code) => Some(match NAMED_CODES.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => name.to_string(),
+                None => format!("{:?}", code),
+            }),
+        };
+        let mut prefix = String::new();
+        for (held, letter) in [
+            (self.modifiers.ctrl, "C-"),
+            (self.modifiers.alt, "A-"),
+            (self.modifiers.shift, "S-"),
+            (self.modifiers.logo, "D-"),
+        ]
+        .iter()
+        {
+            if *held {
+                prefix.push_str(letter);
+            }
+        }
+        match (key_name, self.key) {
+            (None, Key::Char(c)) if prefix.is_empty() => c.to_string(),
+            (None, Key::Char(c)) => format!("<{}{}>", prefix, c),
+            (Some(name), _) => format!("<{}{}>", prefix, name),
+            (None, Key::Code(_)) => unreachable!(),
+        }
+    }
+}
+
+fn parse_named(name: &str, leader: &[KeyEvent]) -> Result<Vec<KeyEvent>, Error> {
+    if name.to_lowercase() == "leader" {
+        return Ok(leader.to_vec());
+    }
+    let mut modifiers = Modifiers::default();
+    let mut rest = name;
+    // `<C-->` is ctrl + minus, so the key itself is never eaten as a modifier
+    while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+        match rest.as_bytes()[0].to_ascii_lowercase() {
+            b'c' => modifiers.ctrl = true,
+            b'a' | b'm' => modifiers.alt = true,
+            b's' => modifiers.shift = true,
+            b'd' => modifiers.logo = true,
+            _ => return Err(Error::InvalidKeys(format!("<{}>", name))),
+        }
+        rest = &rest[2..];
+    }
+    let lower = rest.to_lowercase();
+    let key = if let Some((_, code)) = NAMED_CODES.iter().find(|(named, _)| *named == lower) {
+        Key::Code(*code)
+    } else if let Some((_, c)) = NAMED_CHARS.iter().find(|(named, _)| *named == lower) {
+        Key::Char(*c)
+    } else if rest.chars().count() == 1 && modifiers != Modifiers::default() {
+        Key::Char(rest.chars().next().unwrap_or(' '))
+    } else {
+        return Err(Error::InvalidKeys(format!("<{}>", name)));
+    };
+    Ok(vec![normalize(KeyEvent::new(key, modifiers))])
+}
+
+/// Makes notation line up with what actually comes in from the keyboard
+fn normalize(mut event: KeyEvent) -> KeyEvent {
+    if let Key::Char(c) = event.key {
+        if event.modifiers.is_chord() {
+            // Shortcuts are always reported with the lowercase letter
+            if c.is_ascii_uppercase() {
+                event.key = Key::Char(c.to_ascii_lowercase());
+                event.modifiers.shift = true;
+            }
+        } else if event.modifiers.shift {
+            // Plain shifted text is just the shifted character
+            event.key = Key::Char(c.to_ascii_uppercase());
+            event.modifiers.shift = false;
+        }
+    }
+    event
+}
+
+/// Parses vim style key notation like `<leader>ff`, `<C-s>` or `<S-Tab>`
+pub fn parse_keys(notation: &str, leader: &[KeyEvent]) -> Result<Vec<KeyEvent>, Error> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                if end > 1 {
+                    keys.extend(parse_named(&rest[1..end], leader)?);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        keys.push(KeyEvent::new(Key::Char(c), Modifiers::default()));
+        rest = &rest[c.len_utf8()..];
+    }
+    if keys.is_empty() {
+        return Err(Error::InvalidKeys(notation.to_owned()));
+    }
+    Ok(keys)
+}
@@ -9,16 +9,27 @@ use structopt::StructOpt;
 
 mod buffer;
 mod command;
+mod config_file;
 mod cursor;
 mod error;
+mod file_index;
+mod grep;
 mod handle_command;
+mod history;
 mod input;
+mod messages;
 mod mode;
 mod msg;
+mod picker;
 mod point;
-mod skim_buffer;
+mod quickfix;
+mod registers;
+mod settings;
 mod state;
+mod statusline;
+mod swap;
 mod text_buffer;
+mod view;
 use imgui::*;
 use imgui_wgpu::Renderer;
 use imgui_winit_support;
@@ -30,34 +41,52 @@ use state::State;
 
 use handle_command::handle_command;
 
+use messages::Severity;
+use mode::Mode;
 use msg::{Cmd, InputMsg, Msg};
 
 fn update_state(state: &mut State, msg: Msg, msg_sender: EventLoopProxy<Msg>) -> bool {
-    match msg {
-        Msg::Input(input_msg) => {
-            input::process_input(input_msg, state.mode, |cmd| {
-                msg_sender
-                    .send_event(Msg::Cmd(cmd))
-                    .expect("Failed to create command from input");
-            });
-            false
+    // A whole insert session is undone at once, outside of one every key starts a new change
+    if let Msg::Input(_) = msg {
+        if state.mode != Mode::Insert {
+            state.commit_undo();
         }
-        Msg::Cmd(Cmd::SetStatusText(status)) => {
-            state.status = Some(status);
+    }
+    match msg {
+        Msg::Input(InputMsg::Key(key)) => state.keymap.process(key, state.mode, |cmd| {
+            msg_sender
+                .send_event(Msg::Cmd(cmd))
+                .expect("Failed to create command from input");
+        }),
+        Msg::Input(InputMsg::Timeout) => state.keymap.timeout(state.mode, |cmd| {
+            msg_sender
+                .send_event(Msg::Cmd(cmd))
+                .expect("Failed to create command from input");
+        }),
+        Msg::FileIndex(msg) => state.pickers.update_index(msg),
+        Msg::Grep(msg) => state.pickers.update_search(msg),
+        Msg::Cmd(Cmd::ShowMessage(severity, text)) => {
+            state.messages.push(severity, text);
             true
         }
-        Msg::Cmd(cmd_msg) => match handle_command(state, cmd_msg, msg_sender.clone()) {
-            Ok(should_render) => {
-                state.status = None;
-                should_render
-            }
-            Err(err) => {
-                msg_sender
-                    .send_event(Msg::Cmd(Cmd::SetStatusText(err.to_string())))
-                    .expect("setting error");
-                true
+        Msg::Cmd(cmd_msg) => {
+            state.messages.clear_transient();
+            match handle_command(state, cmd_msg, msg_sender.clone()) {
+                Ok(should_render) => should_render,
+                Err(err) => {
+                    // Causes end up on their own lines so they get the expanded view
+                    let text = err
+                        .chain()
+                        .map(|cause| cause.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n  caused by: ");
+                    msg_sender
+                        .send_event(Msg::Cmd(Cmd::ShowMessage(Severity::Error, text)))
+                        .expect("setting error");
+                    true
+                }
             }
-        },
+        }
     }
 }
 
@@ -69,7 +98,14 @@ fn style(ctx: &mut imgui::Context) {
     style.use_dark_colors();
 }
 
-fn render(ui: &imgui::Ui, state: &mut State, size: &PhysicalSize<u32>) {
+const TAB_BAR_HEIGHT: f32 = 20.;
+
+fn render(
+    ui: &imgui::Ui,
+    state: &mut State,
+    size: &PhysicalSize<u32>,
+    msg_sender: &EventLoopProxy<Msg>,
+) {
     use mode::Mode::*;
     let mut buffer_height = size.height as f32 / 2.;
     let status_window = imgui::Window::new(im_str!("Status"));
@@ -81,33 +117,133 @@ fn render(ui: &imgui::Ui, state: &mut State, size: &PhysicalSize<u32>) {
         .no_decoration()
         .draw_background(false)
         .build(&ui, || {
-            state.mode.render(ui);
+            let view = &state.views[state.current_view()];
+            let pending = state.keymap.pending_keys();
+            let context = statusline::StatusContext {
+                mode: state.mode,
+                buffer: &state.buffers[view.buffer],
+                cursor: &view.cursor,
+                pending: &pending,
+                recording: None, // TODO: wire up once there are macros
+            };
+            let show_left =
+                state.messages.status().is_none() && state.mode != Skim && state.mode != Command;
+            state.status_line.render(ui, &context, show_left);
             match state.mode {
-                Skim => state.skim_buffer.render_bar(ui),
+                Skim => state.pickers.current().render_bar(ui),
                 Command => state.command_buffer.render(ui),
                 _ => {}
             }
-            if let Some(ref status) = &state.status {
-                ui.set_cursor_pos([10., 0.]);
-                let im_string = imgui::ImString::new(status);
-                ui.text(im_string);
-            }
+            state.messages.render_status(ui);
             buffer_height -= ui.window_size()[1];
         });
-    let main_window = imgui::Window::new(im_str!("Main"));
-    main_window
-        .size([size.width as f32 / 2., buffer_height], Condition::Always)
+    let panel_height = state.messages.panel_height(ui);
+    if panel_height > 0. {
+        buffer_height -= panel_height;
+        let messages_window = imgui::Window::new(im_str!("Messages"));
+        messages_window
+            .size([size.width as f32 / 2., panel_height], Condition::Always)
+            .position(
+                [0., size.height as f32 / 2. - 20. - panel_height],
+                Condition::Always,
+            )
+            .movable(false)
+            .scrollable(false)
+            .no_decoration()
+            .build(&ui, || state.messages.render_panel(ui));
+    }
+    let tab_bar_window = imgui::Window::new(im_str!("Tabs"));
+    tab_bar_window
+        .size([size.width as f32 / 2., TAB_BAR_HEIGHT], Condition::Always)
         .position([0., 0.], Condition::Always)
         .movable(false)
+        .scrollable(false)
         .no_decoration()
         .draw_background(false)
         .build(&ui, || {
-            state.update_from_ui(&ui);
-            match state.mode {
-                Normal | Insert | Command | Jump => state.buffers[state.current_buffer].render(ui),
-                Skim => state.skim_buffer.render(ui),
+            let [window_x, window_y] = ui.window_pos();
+            for (index, tab) in state.tabs.iter().enumerate() {
+                let label = ImString::new(format!(" {} ", tab.label(&state.views, &state.buffers)));
+                if index == state.current_tab {
+                    let [cursor_x, cursor_y] = ui.cursor_pos();
+                    let [text_width, _] = ui.calc_text_size(&label, false, 0.);
+                    ui.get_window_draw_list()
+                        .add_rect(
+                            [window_x + cursor_x, window_y + cursor_y],
+                            [window_x + cursor_x + text_width, window_y + TAB_BAR_HEIGHT],
+                            [1., 1., 1., 0.2],
+                        )
+                        .filled(true)
+                        .build();
+                }
+                ui.text(&label);
+                ui.same_line(0.);
+                ui.text("|");
+                ui.same_line(0.);
             }
         });
+    buffer_height -= TAB_BAR_HEIGHT;
+    match state.mode {
+        Normal | Insert | Command => {
+            let main_area = view::Rect {
+                x: 0.,
+                y: TAB_BAR_HEIGHT,
+                width: size.width as f32 / 2.,
+                height: buffer_height,
+            };
+            let current_view = state.current_view();
+            for (view_key, rect) in state.tab().layout.rects(main_area) {
+                let focused = view_key == current_view;
+                let view = &mut state.views[view_key];
+                let buffer = &state.buffers[view.buffer];
+                let gutter = &state.gutter;
+                let settings = &state.settings;
+                let name = ImString::new(format!("View {:?}", view_key));
+                imgui::Window::new(&name)
+                    .size([rect.width, rect.height], Condition::Always)
+                    .position([rect.x, rect.y], Condition::Always)
+                    .movable(false)
+                    // The wheel is handled by the view so it can be configured
+                    .scrollable(false)
+                    .no_decoration()
+                    .draw_background(false)
+                    .build(&ui, || {
+                        if let Some(action) = view.render(ui, buffer, focused, gutter, settings) {
+                            msg_sender
+                                .send_event(Msg::Cmd(Cmd::Mouse(view_key, action)))
+                                .expect("sending mouse event");
+                        }
+                    });
+            }
+        }
+        Skim => {
+            let width = size.width as f32 / 2.;
+            let list_width = if state.settings.preview {
+                width / 2.
+            } else {
+                width
+            };
+            let main_window = imgui::Window::new(im_str!("Main"));
+            main_window
+                .size([list_width, buffer_height], Condition::Always)
+                .position([0., TAB_BAR_HEIGHT], Condition::Always)
+                .movable(false)
+                .no_decoration()
+                .draw_background(false)
+                .build(&ui, || state.pickers.current().render(ui));
+            if state.settings.preview {
+                let picker = state.pickers.current_mut();
+                let config = &state.config;
+                imgui::Window::new(im_str!("Preview"))
+                    .size([width - list_width, buffer_height], Condition::Always)
+                    .position([list_width, TAB_BAR_HEIGHT], Condition::Always)
+                    .movable(false)
+                    .no_decoration()
+                    .draw_background(false)
+                    .build(&ui, || picker.render_preview(ui, config));
+            }
+        }
+    }
 }
 
 #[derive(Debug, StructOpt)]
@@ -129,6 +265,10 @@ struct Opt {
 
     #[structopt(long)]
     metrics: bool,
+
+    /// Defaults to config.json in the user config directory
+    #[structopt(long, parse(from_os_str))]
+    config: Option<std::path::PathBuf>,
 }
 
 fn main() -> Result<()> {
@@ -215,7 +355,8 @@ fn main() -> Result<()> {
     let mut size = window.inner_size();
     flame::end("window setup");
     // END OF SETUP
-    let mut state = State::new()?;
+    let mut state = State::new(config_file::ConfigFile::load(opt.config)?)?;
+    state.swap.install_panic_hook();
 
     let msg_sender = event_loop.create_proxy();
 
@@ -224,6 +365,7 @@ fn main() -> Result<()> {
     }
 
     let mut dirty = false;
+    let mut modifiers = input::Modifiers::default();
 
     window.request_redraw();
     // TODO (perf): Do some performance improvements on this main loop
@@ -235,9 +377,27 @@ fn main() -> Result<()> {
                 if dirty {
                     window.request_redraw();
                 }
+                let timed_out = state
+                    .keymap
+                    .deadline(state.settings.timeout_len)
+                    .map(|deadline| deadline <= Instant::now())
+                    .unwrap_or(false);
+                if timed_out {
+                    let msg = Msg::Input(InputMsg::Timeout);
+                    dirty = update_state(&mut state, msg, msg_sender.clone()) || dirty;
+                }
+                let swap_due = state
+                    .swap
+                    .deadline()
+                    .map(|deadline| deadline <= Instant::now())
+                    .unwrap_or(false);
+                if swap_due {
+                    state.write_swap_files();
+                }
             }
             Event::UserEvent(msg) => {
                 if msg == Msg::Cmd(Cmd::Quit) {
+                    state.swap.clear();
                     if perf {
                         flame::dump_html(&mut std::fs::File::create("flame-graph.html").unwrap())
                             .unwrap();
@@ -245,6 +405,7 @@ fn main() -> Result<()> {
                     *control_flow = ControlFlow::Exit;
                 } else {
                     dirty = update_state(&mut state, msg, msg_sender.clone()) || dirty;
+                    state.swap.track(&state.buffers);
                 }
             }
             Event::WindowEvent {
@@ -287,7 +448,7 @@ fn main() -> Result<()> {
                 let ui = imgui.frame();
 
                 {
-                    render(&ui, &mut state, &size);
+                    render(&ui, &mut state, &size, &msg_sender);
                     if show_metrics {
                         ui.show_metrics_window(&mut true);
                     }
@@ -310,12 +471,29 @@ fn main() -> Result<()> {
                 }
             }
             Event::WindowEvent {
-                event: WindowEvent::ReceivedCharacter(c),
+                event: WindowEvent::Focused(true),
                 ..
             } => {
+                // Whatever ran while the editor was in the background may have changed files
                 msg_sender
-                    .send_event(Msg::Input(InputMsg::CharPressed(c)))
-                    .expect("sending char event");
+                    .send_event(Msg::Cmd(Cmd::CheckDisk))
+                    .expect("sending check disk command");
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(new_modifiers),
+                ..
+            } => {
+                modifiers = new_modifiers.into();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(c),
+                ..
+            } => {
+                if let Some(key) = input::KeyEvent::from_char(c, modifiers) {
+                    msg_sender
+                        .send_event(Msg::Input(InputMsg::Key(key)))
+                        .expect("sending char event");
+                }
             }
             Event::WindowEvent {
                 event:
@@ -330,22 +508,34 @@ fn main() -> Result<()> {
                     },
                 ..
             } => {
-                msg_sender
-                    .send_event(Msg::Input(InputMsg::KeyPressed(keycode)))
-                    .expect("sending key event");
+                if let Some(key) = input::KeyEvent::from_code(keycode, modifiers) {
+                    msg_sender
+                        .send_event(Msg::Input(InputMsg::Key(key)))
+                        .expect("sending key event");
+                }
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => {
                 println!("The close button was pressed; stopping");
+                state.swap.clear();
                 *control_flow = ControlFlow::Exit
             }
             _ => {
-                *control_flow = if should_poll {
-                    ControlFlow::Poll
-                } else {
-                    ControlFlow::Wait
+                let deadline = match (
+                    state.keymap.deadline(state.settings.timeout_len),
+                    state.swap.deadline(),
+                ) {
+                    (Some(keys), Some(swap)) => Some(keys.min(swap)),
+                    (keys, swap) => keys.or(swap),
+                };
+                *control_flow = match deadline {
+                    // Wake up to flush keys that are still waiting on a longer mapping,
+                    // or to write swap files
+                    Some(deadline) => ControlFlow::WaitUntil(deadline),
+                    None if should_poll => ControlFlow::Poll,
+                    None => ControlFlow::Wait,
                 }
             }
         }
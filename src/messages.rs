@@ -0,0 +1,215 @@
+use syntect::highlighting::{Highlighter, Theme};
+use syntect::parsing::Scope;
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_HISTORY: usize = 200;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub severity: Severity,
+    pub text: String,
+    pub time: SystemTime,
+}
+
+impl Message {
+    fn is_multi_line(&self) -> bool {
+        self.text.contains('\n')
+    }
+
+    /// UTC wall clock time, good enough to line messages up with each other
+    fn timestamp(&self) -> String {
+        let seconds = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+            % 86400;
+        format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60
+        )
+    }
+}
+
+fn theme_color(theme: &Theme, scope: Option<&str>) -> [f32; 4] {
+    let syntect::highlighting::Color { r, g, b, a } =
+        match scope.and_then(|scope| Scope::new(scope).ok()) {
+            Some(scope) => Highlighter::new(theme).style_for_stack(&[scope]).foreground,
+            None => theme
+                .settings
+                .foreground
+                .unwrap_or(syntect::highlighting::Color::WHITE),
+        };
+    [
+        r as f32 / 255.,
+        g as f32 / 255.,
+        b as f32 / 255.,
+        a as f32 / 255.,
+    ]
+}
+
+/// Everything that was shown in the status bar, plus whatever is on screen right now
+pub struct MessageLog {
+    history: VecDeque<Message>,
+    current: Option<Message>,
+    /// Shown whenever nothing else is, until it's unpinned
+    pinned: Option<Message>,
+    show_history: bool,
+    info_color: [f32; 4],
+    warn_color: [f32; 4],
+    error_color: [f32; 4],
+}
+
+impl MessageLog {
+    pub fn new(theme: &Theme) -> MessageLog {
+        MessageLog {
+            history: VecDeque::new(),
+            current: None,
+            pinned: None,
+            show_history: false,
+            info_color: theme_color(theme, None),
+            warn_color: theme_color(theme, Some("markup.changed")),
+            error_color: theme_color(theme, Some("markup.deleted")),
+        }
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.info_color = theme_color(theme, None);
+        self.warn_color = theme_color(theme, Some("markup.changed"));
+        self.error_color = theme_color(theme, Some("markup.deleted"));
+    }
+
+    fn color(&self, severity: Severity) -> [f32; 4] {
+        match severity {
+            Severity::Info => self.info_color,
+            Severity::Warn => self.warn_color,
+            Severity::Error => self.error_color,
+        }
+    }
+
+    pub fn push(&mut self, severity: Severity, text: String) {
+        let message = Message {
+            severity,
+            text,
+            time: SystemTime::now(),
+        };
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(message.clone());
+        self.current = Some(message);
+    }
+
+    /// For a question that needs an answer, it comes back after other messages go away
+    pub fn pin(&mut self, severity: Severity, text: String) {
+        self.push(severity, text);
+        self.pinned = self.current.clone();
+    }
+
+    pub fn unpin(&mut self) {
+        self.pinned = None;
+    }
+
+    /// Single line messages go away on the next command, multi-line ones wait to be dismissed
+    pub fn clear_transient(&mut self) {
+        if self
+            .current
+            .as_ref()
+            .map(|message| !message.is_multi_line())
+            .unwrap_or(false)
+        {
+            self.current = None;
+        }
+    }
+
+    pub fn dismiss(&mut self) -> bool {
+        let was_showing = self.current.is_some() || self.show_history;
+        self.current = None;
+        self.show_history = false;
+        was_showing
+    }
+
+    pub fn show_history(&mut self) {
+        self.show_history = true;
+    }
+
+    /// The message that fits in the status bar, if any
+    pub fn status(&self) -> Option<&Message> {
+        self.current
+            .as_ref()
+            .filter(|message| !message.is_multi_line())
+            .or_else(|| self.pinned.as_ref())
+    }
+
+    fn panel_lines(&self) -> Vec<([f32; 4], String)> {
+        if self.show_history {
+            self.history
+                .iter()
+                .flat_map(|message| {
+                    let color = self.color(message.severity);
+                    let timestamp = message.timestamp();
+                    message
+                        .text
+                        .lines()
+                        .enumerate()
+                        .map(move |(index, line)| {
+                            if index == 0 {
+                                (color, format!("{} {}", timestamp, line))
+                            } else {
+                                (color, format!("         {}", line))
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        } else {
+            match self.current {
+                Some(ref message) if message.is_multi_line() => {
+                    let color = self.color(message.severity);
+                    message
+                        .text
+                        .lines()
+                        .map(|line| (color, line.to_owned()))
+                        .collect()
+                }
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    /// How many lines need to be shown above the status bar
+    pub fn panel_height(&self, ui: &imgui::Ui) -> f32 {
+        let lines = self.panel_lines().len();
+        if lines == 0 {
+            0.
+        } else {
+            // One extra for the dismiss hint
+            (lines + 1) as f32 * ui.text_line_height_with_spacing()
+        }
+    }
+
+    pub fn render_panel(&self, ui: &imgui::Ui) {
+        for (color, line) in self.panel_lines() {
+            ui.text_colored(color, &line);
+        }
+        ui.text_colored(self.info_color, "Press Enter or Esc to continue");
+    }
+
+    pub fn render_status(&self, ui: &imgui::Ui) {
+        if let Some(message) = self.status() {
+            ui.set_cursor_pos([10., 0.]);
+            ui.text_colored(self.color(message.severity), &message.text);
+        }
+    }
+}
@@ -1,29 +1,29 @@
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Mode {
     Normal,
     Insert,
     Command,
-    Jump,
     Skim,
     // Select,
 }
 
 impl Mode {
-    fn as_str(self) -> &'static str {
+    /// Names used for keymaps in the config file
+    pub fn from_name(name: &str) -> Option<Mode> {
+        match name.to_lowercase().as_str() {
+            "normal" => Some(Mode::Normal),
+            "insert" => Some(Mode::Insert),
+            "command" => Some(Mode::Command),
+            "skim" => Some(Mode::Skim),
+            _ => None,
+        }
+    }
+    pub fn as_str(self) -> &'static str {
         match self {
             Mode::Normal => "Normal",
             Mode::Insert => "Insert",
             Mode::Command => "Command",
-            Mode::Jump => "Jump",
             Mode::Skim => "Skim",
         }
     }
-    pub fn render(self, ui: &imgui::Ui) {
-        let value = self.as_str();
-        let [width, _height] = ui.window_size();
-        let im_string = imgui::ImString::new(value);
-        let [text_width, _text_height] = ui.calc_text_size(&im_string, false, width);
-        ui.set_cursor_pos([width - text_width - 15., 0.]);
-        ui.text(im_string);
-    }
 }
@@ -1,12 +1,15 @@
 #![allow(dead_code)]
 
-use crate::mode::Mode;
-use winit::event::VirtualKeyCode;
+use crate::{
+    buffer::BufferKey, file_index::IndexMsg, grep::GrepMsg, input::KeyEvent, messages::Severity,
+    mode::Mode, point::Point, view::ViewKey,
+};
 
 #[derive(PartialEq, Debug)]
 pub enum InputMsg {
-    CharPressed(char),
-    KeyPressed(VirtualKeyCode),
+    Key(KeyEvent),
+    /// `timeoutlen` passed with keys still pending
+    Timeout,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -17,13 +20,60 @@ pub enum Direction {
     Down,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// What to do with a swap file left behind by a crash
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SwapAction {
+    /// Takes its text as unsaved changes
+    Recover,
+    /// Opens it next to the buffer to compare
+    Diff,
+    Delete,
+}
+
+/// What to do about a file that another program changed under its buffer
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DiskAction {
+    /// Throws away the buffer's changes
+    Reload,
+    /// Sticks with the buffer, so saving overwrites the file
+    Keep,
+    /// Opens what is on disk next to the buffer to compare
+    Diff,
+}
+
+/// Where a picker opens what was picked, besides the current view
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OpenIn {
+    Split(SplitDirection),
+    Tab,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DeleteDirection {
     Before,
     After,
 }
 
-#[derive(Debug, PartialEq)]
+/// f/F/t/T and the two character sneak
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharSearch {
+    pub target: String,
+    pub forward: bool,
+    /// Stops right before the match, like `t`
+    pub till: bool,
+    /// Keeps looking past the current line
+    pub multi_line: bool,
+    /// Set for `;` and `,` so `t` doesn't get stuck right in front of its last match
+    pub repeat: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum JumpType {
     EndOfLine,
     StartOfLine,
@@ -34,24 +84,145 @@ pub enum JumpType {
     PrevWord,
     PageForward,
     PageBackward,
+    /// Counted from 1, like line numbers
+    Line(usize),
+    Find(CharSearch),
+    /// The last `Find` again, the other way around if `true`
+    RepeatFind(bool),
+    /// `%`
+    MatchingBracket,
+    /// Straight to a spot, like a grep result
+    Position(Point),
+}
+
+/// A spot in a file, for the quickfix list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub path: std::path::PathBuf,
+    pub position: Point,
+    /// Shown next to it
+    pub text: String,
+}
+
+impl Location {
+    /// What opens the file with the cursor on the spot
+    pub fn cmds(&self) -> Vec<Cmd> {
+        vec![
+            Cmd::LoadFile(self.path.clone()),
+            Cmd::Jump(JumpType::Position(self.position)),
+        ]
+    }
+}
+
+/// What the mouse did inside a view, already in buffer coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum MouseAction {
+    Click(Point),
+    /// Moves the cursor with the button held, selecting from where it was
+    Drag(Point),
+    SelectWord(Point),
+    /// First and last line, in the order they were dragged over
+    SelectLines(usize, usize),
+    /// Lines to scroll by, negative is up
+    Scroll(isize),
+}
+
+/// What a picker in Skim mode goes through
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PickerKind {
+    Files,
+    Buffers,
+    Commands,
+    Lines,
+    Recent,
+    Registers,
+    Marks,
+    Themes,
+    Grep,
+    Quickfix,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Cmd {
     MoveCursor(Direction),
     Quit,
     ChangeMode(Mode),
     InsertChar(char, bool),
-    SetStatusText(String),
+    /// Inserted as is, without auto-indent
+    InsertText(String),
+    ShowMessage(Severity, String),
+    ShowMessages,
+    DismissMessages,
     Submit,
+    /// Like `Submit` in a picker, with every item in a new split or tab
+    SubmitIn(OpenIn),
+    /// Adds the picker's selected item to the ones opened together, or takes it back out
+    TogglePicked,
     // InsertCharAtPoint(char, Point),
     // InsertStringAtPoint(String, Point),
     // DeleteCharRange(Point, Point),
     DeleteChar(DeleteDirection),
     Jump(JumpType),
-    // RunCommand,
+    RunCommand(String),
+    /// Mode, keys, what they map to and whether user maps apply to the result
+    Map(Mode, String, String, bool),
     WriteBuffer(Option<std::path::PathBuf>),
     LoadFile(std::path::PathBuf),
+    SplitView(SplitDirection),
+    FocusView(Direction),
+    CycleView,
+    CloseView,
+    OnlyView,
+    /// Delta is in percent of the enclosing split
+    ResizeView(SplitDirection, i16),
+    SetOption(String),
+    NewTab,
+    CloseTab,
+    NextTab,
+    PrevTab,
+    Mouse(ViewKey, MouseAction),
+    /// A count typed before the keys, like `5j`
+    Repeat(usize, Box<Cmd>),
+    /// Deletes from the cursor to wherever the jump lands, into the unnamed register
+    DeleteMotion(JumpType),
+    /// Deletes the cursor's line into the unnamed register
+    DeleteLine,
+    /// Copies the selection, or the current line, into a register
+    Yank(char),
+    /// Register and whether it goes after the cursor
+    Paste(char, bool),
+    /// `m{a-zA-Z}`
+    SetMark(char),
+    /// Goes to the exact spot for `true`, the start of the line otherwise
+    JumpToMark(char, bool),
+    /// Ctrl-O
+    OlderJump,
+    /// Ctrl-I
+    NewerJump,
+    /// `g;`
+    OlderChange,
+    /// `g,`
+    NewerChange,
+    Undo,
+    /// Ctrl-R
+    Redo,
+    OpenPicker(PickerKind),
+    /// Shows a loaded buffer in the current view
+    ShowBuffer(BufferKey),
+    /// Name of one of syntect's default themes
+    SetTheme(String),
+    /// Sends what the picker is showing to the quickfix list
+    ExportQuickfix,
+    SetQuickfix(Vec<Location>),
+    NextQuickfix,
+    PrevQuickfix,
+    GoToQuickfix(usize),
+    /// For the current buffer's file
+    Swap(SwapAction),
+    /// Reloads buffers whose files changed on disk, or warns about them if they have changes
+    CheckDisk,
+    /// For the current buffer's file
+    Disk(DiskAction),
     // BufferLoaded,
     // BufferModified,
     // SearchFiles,
@@ -66,4 +237,8 @@ pub enum Cmd {
 pub enum Msg {
     Input(InputMsg),
     Cmd(Cmd),
+    /// Results from the background file walk
+    FileIndex(IndexMsg),
+    /// Results from the background content search
+    Grep(GrepMsg),
 }
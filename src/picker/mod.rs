@@ -0,0 +1,597 @@
+use crate::{
+    buffer::get_visible_lines,
+    file_index::IndexMsg,
+    grep::GrepMsg,
+    history::FileHistory,
+    mode::Mode,
+    msg::{Cmd, Direction, JumpType, Location, Msg, OpenIn},
+    state::Config,
+    text_buffer::TextBuffer,
+};
+
+use anyhow::Result;
+
+use winit::event_loop::EventLoopProxy;
+
+use fuzzy_matcher::skim::fuzzy_indices;
+
+use std::cell::Cell;
+
+mod preview;
+mod sources;
+pub use preview::{Preview, PreviewTarget};
+pub use sources::{
+    BufferSource, CommandSource, FileSource, GrepSource, LineSource, MarkSource, QuickfixSource,
+    RecentSource, RegisterSource, ThemeSource,
+};
+
+/// A match in the emphasized part beats the same letters spread over the rest
+const EMPHASIS_BONUS: i64 = 50;
+const DIM_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.];
+const EMPHASIS_COLOR: [f32; 4] = [1., 1., 1., 1.];
+const MATCH_COLOR: [f32; 4] = [0.92, 0.8, 0.55, 1.];
+const SCORE_COLOR: [f32; 4] = [0.45, 0.45, 0.45, 1.];
+const SELECTED_COLOR: [f32; 4] = [1., 1., 1., 0.2];
+
+/// Where a picker's items come from and what picking one does
+pub trait Source {
+    type Item;
+    /// Shown in front of the query
+    fn prompt(&self) -> &str;
+    fn items(&self) -> &[Self::Item];
+    fn display(&self, item: &Self::Item) -> String;
+    /// Added to the fuzzy score. Without a query, items are ordered by it.
+    fn boost(&self, _item: &Self::Item) -> i64 {
+        0
+    }
+    /// Char index in the displayed `text` where the part worth emphasizing starts,
+    /// like the name of a file after its directories
+    fn emphasis(&self, _item: &Self::Item, _text: &str) -> usize {
+        0
+    }
+    /// Run after going back to Normal mode
+    fn select(&self, item: &Self::Item) -> Vec<Cmd>;
+    /// Where `item` is, for sending it to the quickfix list
+    fn location(&self, _item: &Self::Item) -> Option<Location> {
+        None
+    }
+    /// File shown next to the list while `item` is selected
+    fn preview(&self, _item: &Self::Item) -> Option<PreviewTarget> {
+        None
+    }
+    /// Whether the query fuzzy filters the items. Sources that do their own
+    /// searching turn this off and get the query through `query_changed`.
+    fn fuzzy(&self) -> bool {
+        true
+    }
+    fn query_changed(&mut self, _query: &str, _msg_sender: &EventLoopProxy<Msg>) {}
+    /// Char indices of the displayed `text` to highlight when not fuzzy
+    fn matched(&self, _item: &Self::Item, _text: &str) -> Vec<usize> {
+        Vec::new()
+    }
+    /// Results from a background search. True if the items changed.
+    fn update(&mut self, _msg: GrepMsg) -> bool {
+        false
+    }
+}
+
+/// An item that matches the query
+struct Entry {
+    /// Into the source's items
+    index: usize,
+    /// Only for fuzzy matches, boost included
+    score: Option<i64>,
+    /// What entries are sorted by
+    rank: i64,
+    text: String,
+    /// Char indices into `text`, sorted
+    matched: Vec<usize>,
+    emphasis: usize,
+}
+
+impl Entry {
+    fn new(index: usize, text: String, emphasis: usize, query: &str, boost: i64) -> Option<Entry> {
+        // Everything matches, boosted ones first
+        if query.is_empty() {
+            return Some(Entry {
+                index,
+                score: None,
+                rank: boost,
+                text,
+                matched: Vec::new(),
+                emphasis,
+            });
+        }
+        let emphasis_byte = text
+            .char_indices()
+            .nth(emphasis)
+            .map_or(text.len(), |(byte, _)| byte);
+        let emphasis_match =
+            fuzzy_indices(&text[emphasis_byte..], query).map(|(score, matched)| {
+                let matched = matched.into_iter().map(|i| i + emphasis).collect();
+                (score + EMPHASIS_BONUS, matched)
+            });
+        let (score, matched) = match (emphasis_match, fuzzy_indices(&text, query)) {
+            (Some(emphasized), Some(whole)) if whole.0 > emphasized.0 => whole,
+            (Some(emphasized), _) => emphasized,
+            (None, whole) => whole?,
+        };
+        Some(Entry {
+            index,
+            score: Some(score + boost),
+            rank: score + boost,
+            text,
+            matched,
+            emphasis,
+        })
+    }
+
+    fn color(&self, char_index: usize) -> [f32; 4] {
+        if self.matched.binary_search(&char_index).is_ok() {
+            MATCH_COLOR
+        } else if char_index >= self.emphasis {
+            EMPHASIS_COLOR
+        } else {
+            DIM_COLOR
+        }
+    }
+
+    /// Score first, then the text split into runs of the same color
+    fn render(&self, ui: &imgui::Ui) {
+        let draw = |color: [f32; 4], text: &str| {
+            ui.text_colored(color, text);
+            ui.same_line(0.);
+        };
+        if let Some(score) = self.score {
+            draw(SCORE_COLOR, &format!("{:>5} ", score));
+        }
+        let mut run = String::new();
+        let mut run_color = None;
+        for (char_index, c) in self.text.chars().enumerate() {
+            let color = self.color(char_index);
+            if run_color != Some(color) {
+                if let Some(run_color) = run_color {
+                    draw(run_color, &run);
+                }
+                run.clear();
+                run_color = Some(color);
+            }
+            run.push(c);
+        }
+        if let Some(run_color) = run_color {
+            draw(run_color, &run);
+        }
+        ui.new_line();
+    }
+}
+
+/// Fuzzy finds through whatever `S` provides
+pub struct Picker<S: Source> {
+    pub source: S,
+    query: TextBuffer,
+    /// Into `entries`, 0 is the best match and sits right above the prompt
+    selected_option: usize,
+    entries: Vec<Entry>,
+    /// Displayed text of the items Tab picked, in the order they were picked
+    picked: Vec<String>,
+    /// Entries that fit in the window, as of the last render
+    page_size: Cell<usize>,
+    /// Loaded when it's first drawn, so scrolling past items doesn't read them
+    preview: Option<Preview>,
+}
+
+impl<S: Source> Picker<S> {
+    pub fn new(source: S) -> Picker<S> {
+        let mut picker = Picker {
+            source,
+            query: TextBuffer::default(),
+            selected_option: 0,
+            entries: Vec::new(),
+            picked: Vec::new(),
+            page_size: Cell::new(1),
+            preview: None,
+        };
+        picker.update_filtered_entries();
+        picker
+    }
+
+    /// Empties the query
+    pub fn reset(&mut self) {
+        self.query.clear();
+        self.picked.clear();
+        self.update_filtered_entries();
+    }
+
+    /// For items that changed while the picker is open, the selection stays put
+    pub fn refresh(&mut self) {
+        let selected = self.selected_option;
+        self.update_filtered_entries();
+        self.selected_option = selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Up a line past the last entry comes back around to the first
+    fn move_selection(&mut self, direction: Direction) {
+        let len = self.entries.len();
+        self.selected_option = match direction {
+            Direction::Up if self.selected_option + 1 < len => self.selected_option + 1,
+            Direction::Up => 0,
+            Direction::Down if self.selected_option > 0 => self.selected_option - 1,
+            Direction::Down => len.saturating_sub(1),
+            Direction::Left | Direction::Right => self.selected_option,
+        };
+    }
+
+    /// Pages stop at either end instead of wrapping
+    fn move_page(&mut self, up: bool) {
+        let page = self.page_size.get().max(1);
+        self.selected_option = if up {
+            (self.selected_option + page).min(self.entries.len().saturating_sub(1))
+        } else {
+            self.selected_option.saturating_sub(page)
+        };
+    }
+
+    fn toggle_picked(&mut self) {
+        let text = match self.entries.get(self.selected_option) {
+            Some(entry) => &entry.text,
+            None => return,
+        };
+        match self.picked.iter().position(|picked| picked == text) {
+            Some(position) => {
+                self.picked.remove(position);
+            }
+            None => self.picked.push(text.clone()),
+        }
+        self.move_selection(Direction::Up);
+    }
+
+    /// What Tab picked, or the selected item if nothing was
+    fn picked_items(&self) -> Vec<&S::Item> {
+        let items = self.source.items();
+        let picked: Vec<&S::Item> = self
+            .picked
+            .iter()
+            .filter_map(|text| items.iter().find(|item| self.source.display(item) == *text))
+            .collect();
+        if !picked.is_empty() {
+            return picked;
+        }
+        self.entries
+            .get(self.selected_option)
+            .map(|entry| &items[entry.index])
+            .into_iter()
+            .collect()
+    }
+
+    /// Goes back to Normal mode and runs what picking the items does
+    fn submit(&mut self, open_in: Option<OpenIn>, msg_sender: &EventLoopProxy<Msg>) {
+        let mut cmds = vec![Cmd::ChangeMode(Mode::Normal)];
+        for item in self.picked_items() {
+            match open_in {
+                Some(OpenIn::Split(direction)) => cmds.push(Cmd::SplitView(direction)),
+                Some(OpenIn::Tab) => cmds.push(Cmd::NewTab),
+                None => {}
+            }
+            cmds.extend(self.source.select(item));
+        }
+        for cmd in cmds {
+            msg_sender
+                .send_event(Msg::Cmd(cmd))
+                .expect("sending picked command");
+        }
+        self.query.clear();
+        self.picked.clear();
+    }
+
+    fn update_filtered_entries(&mut self) {
+        let query = self.query.as_str();
+        let source = &self.source;
+        self.entries = source
+            .items()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                let text = source.display(item);
+                let emphasis = source.emphasis(item, &text);
+                if !source.fuzzy() {
+                    let matched = source.matched(item, &text);
+                    return Some(Entry {
+                        index,
+                        score: None,
+                        rank: 0,
+                        text,
+                        matched,
+                        emphasis,
+                    });
+                }
+                Entry::new(index, text, emphasis, query, source.boost(item))
+            })
+            .collect();
+        // Stable, so ties stay in the order the source has them
+        self.entries.sort_by(|a, b| b.rank.cmp(&a.rank));
+        self.selected_option = 0;
+    }
+}
+
+/// What Skim mode needs from a picker, whatever its source is
+pub trait AnyPicker {
+    fn handle_command(&mut self, cmd: Cmd, msg_sender: EventLoopProxy<Msg>) -> Result<bool>;
+    /// True if the items changed
+    fn update(&mut self, msg: GrepMsg) -> bool;
+    fn render(&self, ui: &imgui::Ui);
+    fn render_bar(&self, ui: &imgui::Ui);
+    /// Nothing is drawn if the selected item has nothing to preview
+    fn render_preview(&mut self, ui: &imgui::Ui, config: &Config);
+}
+
+impl<S: Source> AnyPicker for Picker<S> {
+    fn handle_command(&mut self, cmd: Cmd, msg_sender: EventLoopProxy<Msg>) -> Result<bool> {
+        let should_render = match cmd {
+            Cmd::Submit => {
+                self.submit(None, &msg_sender);
+                true
+            }
+            Cmd::SubmitIn(open_in) => {
+                self.submit(Some(open_in), &msg_sender);
+                true
+            }
+            Cmd::TogglePicked => {
+                self.toggle_picked();
+                true
+            }
+            Cmd::ExportQuickfix => {
+                let items = self.source.items();
+                let locations: Vec<Location> = self
+                    .entries
+                    .iter()
+                    .filter_map(|entry| self.source.location(&items[entry.index]))
+                    .collect();
+                if locations.is_empty() {
+                    return Ok(false);
+                }
+                msg_sender
+                    .send_event(Msg::Cmd(Cmd::ChangeMode(Mode::Normal)))
+                    .expect("changing mode");
+                msg_sender
+                    .send_event(Msg::Cmd(Cmd::SetQuickfix(locations)))
+                    .expect("sending quickfix list");
+                self.query.clear();
+                self.picked.clear();
+                true
+            }
+            Cmd::MoveCursor(direction @ Direction::Up)
+            | Cmd::MoveCursor(direction @ Direction::Down) => {
+                self.move_selection(direction);
+                true
+            }
+            Cmd::Jump(JumpType::PageBackward) => {
+                self.move_page(true);
+                true
+            }
+            Cmd::Jump(JumpType::PageForward) => {
+                self.move_page(false);
+                true
+            }
+            cmd => {
+                let updated = self.query.handle_command(cmd)?;
+                if updated {
+                    self.source.query_changed(self.query.as_str(), &msg_sender);
+                    if !self.source.fuzzy() {
+                        // The old results are gone
+                        self.picked.clear();
+                    }
+                    self.update_filtered_entries();
+                }
+                updated
+            }
+        };
+        Ok(should_render)
+    }
+
+    fn update(&mut self, msg: GrepMsg) -> bool {
+        let changed = self.source.update(msg);
+        if changed {
+            self.refresh();
+        }
+        changed
+    }
+
+    /// Best match at the bottom, scrolled so the selection is always showing
+    fn render(&self, ui: &imgui::Ui) {
+        let [width, _height] = ui.window_content_region_max();
+        let line_height = ui.text_line_height_with_spacing();
+        let lines = get_visible_lines(ui).max(1);
+        self.page_size.set(lines);
+        let first = (self.selected_option + 1).saturating_sub(lines);
+        let shown = self.entries.iter().enumerate().skip(first).take(lines);
+        for (index, entry) in shown.rev() {
+            if self.selected_option == index {
+                let [x, y] = ui.cursor_screen_pos();
+                ui.get_window_draw_list()
+                    .add_rect([x, y], [x + width, y + line_height], SELECTED_COLOR)
+                    .filled(true)
+                    .build();
+            }
+            let marker = if self.picked.contains(&entry.text) {
+                "+"
+            } else {
+                " "
+            };
+            ui.text_colored(MATCH_COLOR, marker);
+            ui.same_line(0.);
+            entry.render(ui);
+        }
+    }
+
+    fn render_bar(&self, ui: &imgui::Ui) {
+        self.query.render_with_prompt(ui, self.source.prompt());
+    }
+
+    fn render_preview(&mut self, ui: &imgui::Ui, config: &Config) {
+        let target = match self
+            .entries
+            .get(self.selected_option)
+            .and_then(|entry| self.source.preview(&self.source.items()[entry.index]))
+        {
+            Some(target) => target,
+            None => return,
+        };
+        if !self
+            .preview
+            .as_ref()
+            .map_or(false, |preview| preview.shows(&target))
+        {
+            self.preview = Some(Preview::load(target, config));
+        }
+        if let Some(ref mut preview) = self.preview {
+            preview.render(ui);
+        }
+    }
+}
+
+/// The picker Skim mode is showing. The file picker sticks around between
+/// uses so its index doesn't have to be walked from scratch.
+pub struct Pickers {
+    files: Picker<FileSource>,
+    other: Option<Box<dyn AnyPicker>>,
+}
+
+impl Default for Pickers {
+    fn default() -> Pickers {
+        Pickers {
+            files: Picker::new(FileSource::default()),
+            other: None,
+        }
+    }
+}
+
+impl Pickers {
+    /// The files from last time show up right away, the walk fills in the rest
+    pub fn open_files(
+        &mut self,
+        msg_sender: EventLoopProxy<Msg>,
+        hidden: bool,
+        history: &FileHistory,
+    ) {
+        self.other = None;
+        self.files.source.index.refresh(msg_sender, hidden);
+        self.files.source.rank_by(history);
+        self.files.reset();
+    }
+
+    pub fn open(&mut self, picker: Box<dyn AnyPicker>) {
+        self.other = Some(picker);
+    }
+
+    pub fn current(&self) -> &dyn AnyPicker {
+        match self.other {
+            Some(ref picker) => picker.as_ref(),
+            None => &self.files,
+        }
+    }
+
+    pub fn current_mut(&mut self) -> &mut dyn AnyPicker {
+        match self.other {
+            Some(ref mut picker) => picker.as_mut(),
+            None => &mut self.files,
+        }
+    }
+
+    /// True if the picker that is showing got new results
+    pub fn update_search(&mut self, msg: GrepMsg) -> bool {
+        match self.other {
+            Some(ref mut picker) => picker.update(msg),
+            None => false,
+        }
+    }
+
+    /// True if the file picker is showing and its files changed
+    pub fn update_index(&mut self, msg: IndexMsg) -> bool {
+        if !self.files.source.index.update(msg) {
+            return false;
+        }
+        self.files.refresh();
+        self.other.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Paths with the file name emphasized, boosted by how often they were "opened"
+    struct TestSource {
+        items: Vec<(&'static str, i64)>,
+    }
+
+    impl Source for TestSource {
+        type Item = (&'static str, i64);
+
+        fn prompt(&self) -> &str {
+            ""
+        }
+
+        fn items(&self) -> &[Self::Item] {
+            &self.items
+        }
+
+        fn display(&self, item: &Self::Item) -> String {
+            item.0.to_owned()
+        }
+
+        fn boost(&self, item: &Self::Item) -> i64 {
+            item.1
+        }
+
+        fn emphasis(&self, _item: &Self::Item, text: &str) -> usize {
+            text.rfind('/')
+                .map_or(0, |slash| text[..=slash].chars().count())
+        }
+
+        fn select(&self, _item: &Self::Item) -> Vec<Cmd> {
+            Vec::new()
+        }
+    }
+
+    fn picker(items: Vec<(&'static str, i64)>, query: &str) -> Picker<TestSource> {
+        let mut picker = Picker::new(TestSource { items });
+        for c in query.chars() {
+            picker
+                .query
+                .handle_command(Cmd::InsertChar(c, true))
+                .unwrap();
+        }
+        picker.update_filtered_entries();
+        picker
+    }
+
+    fn ranked(picker: &Picker<TestSource>) -> Vec<&str> {
+        picker
+            .entries
+            .iter()
+            .map(|entry| entry.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn file_name_matches_first() {
+        let picker = picker(
+            vec![("main/src.rs", 0), ("src/main.rs", 0), ("lib.rs", 0)],
+            "main",
+        );
+        assert_eq!(ranked(&picker), vec!["src/main.rs", "main/src.rs"]);
+    }
+
+    #[test]
+    fn boost_orders_an_empty_query() {
+        let picker = picker(vec![("a", 0), ("b", 3), ("c", 0), ("d", 1)], "");
+        assert_eq!(ranked(&picker), vec!["b", "d", "a", "c"]);
+        assert!(picker.entries.iter().all(|entry| entry.score.is_none()));
+    }
+
+    #[test]
+    fn boost_adds_to_the_score() {
+        let picker = picker(vec![("src/lib.rs", 0), ("old/lib.rs", 1000)], "lib");
+        assert_eq!(ranked(&picker), vec!["old/lib.rs", "src/lib.rs"]);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    buffer::{highlighter_for, HighlightContainer},
+    state::Config,
+};
+
+use ropey::Rope;
+
+use std::{borrow::Cow, path::PathBuf};
+
+/// Bigger files aren't read just to look at them
+const MAX_PREVIEW_BYTES: u64 = 512 * 1024;
+/// Lines shown above the one being previewed
+const CONTEXT_LINES: usize = 5;
+const LINE_COLOR: [f32; 4] = [1., 1., 1., 0.1];
+const MESSAGE_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.];
+
+/// What a picker item wants shown next to the list
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewTarget {
+    pub path: PathBuf,
+    /// Scrolled to and marked, counted from 0
+    pub line: Option<usize>,
+}
+
+/// A highlighted look at the file under the picker's selection
+pub struct Preview {
+    target: PreviewTarget,
+    rope: Rope,
+    highlighter: Option<HighlightContainer>,
+    /// Shown instead of the file when it couldn't be read
+    message: Option<String>,
+    /// Only scrolls once, after that the window keeps its own scroll
+    scrolled: bool,
+}
+
+impl Preview {
+    pub fn load(target: PreviewTarget, config: &Config) -> Preview {
+        let mut preview = Preview {
+            target,
+            rope: Rope::new(),
+            highlighter: None,
+            message: None,
+            scrolled: false,
+        };
+        let path = &preview.target.path;
+        let result = std::fs::metadata(path).and_then(|metadata| {
+            if metadata.len() > MAX_PREVIEW_BYTES {
+                Ok(None)
+            } else {
+                std::fs::File::open(path)
+                    .and_then(Rope::from_reader)
+                    .map(Some)
+            }
+        });
+        match result {
+            Ok(Some(rope)) => {
+                preview.highlighter = highlighter_for(path, &rope, config);
+                preview.rope = rope;
+            }
+            Ok(None) => preview.message = Some("Too big to preview".to_owned()),
+            Err(err) => preview.message = Some(err.to_string()),
+        }
+        preview
+    }
+
+    pub fn shows(&self, target: &PreviewTarget) -> bool {
+        self.target == *target
+    }
+
+    /// Has to run inside the preview's window
+    pub fn render(&mut self, ui: &imgui::Ui) {
+        if let Some(ref message) = self.message {
+            ui.text_colored(MESSAGE_COLOR, message);
+            return;
+        }
+        let line_height = ui.text_line_height_with_spacing();
+        if !self.scrolled {
+            let top = self.target.line.unwrap_or(0).saturating_sub(CONTEXT_LINES);
+            ui.set_scroll_y(top as f32 * line_height);
+            self.scrolled = true;
+        }
+        if let Some(line) = self.target.line {
+            let [window_x, window_y] = ui.window_pos();
+            let [width, _] = ui.window_size();
+            let top = window_y + line as f32 * line_height - ui.scroll_y();
+            ui.get_window_draw_list()
+                .add_rect(
+                    [window_x, top],
+                    [window_x + width, top + line_height],
+                    LINE_COLOR,
+                )
+                .filled(true)
+                .build();
+        }
+        match self.highlighter {
+            Some(ref highlighter) => highlighter.render(&self.rope.slice(..), ui, false),
+            None => {
+                for line in self.rope.lines() {
+                    let text: Cow<str> = line.into();
+                    ui.text(text);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,539 @@
+use super::{PreviewTarget, Source};
+use crate::{
+    buffer::BufferKey,
+    command::{Command, COMMANDS},
+    file_index::FileIndex,
+    grep::{Grep, GrepMatch, GrepMsg},
+    history::FileHistory,
+    mode::Mode,
+    msg::{Cmd, JumpType, Location, Msg},
+    point::Point,
+    state::State,
+};
+
+use winit::event_loop::EventLoopProxy;
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Most a file's history can add to its fuzzy score
+const MAX_FRECENCY_BONUS: i64 = 100;
+
+/// Where the file name starts, so it gets emphasized over the directories
+fn file_name_start(text: &str) -> usize {
+    text.rfind(std::path::is_separator)
+        .map_or(0, |separator| text[..=separator].chars().count())
+}
+
+/// Files under the working directory, shown relative to it. The ones opened
+/// often and lately rank higher.
+#[derive(Default)]
+pub struct FileSource {
+    pub index: FileIndex,
+    /// Relative to the working directory, without a leading `./`
+    boosts: HashMap<PathBuf, i64>,
+}
+
+impl FileSource {
+    pub fn rank_by(&mut self, history: &FileHistory) {
+        self.boosts.clear();
+        let cwd = match std::env::current_dir().and_then(std::fs::canonicalize) {
+            Ok(cwd) => cwd,
+            Err(_) => return,
+        };
+        for path in history.ranked() {
+            if let Ok(relative) = path.strip_prefix(&cwd) {
+                let boost = (history.frecency(&path) * 10.).round() as i64;
+                self.boosts
+                    .insert(relative.to_path_buf(), boost.min(MAX_FRECENCY_BONUS));
+            }
+        }
+    }
+}
+
+impl Source for FileSource {
+    type Item = PathBuf;
+
+    fn prompt(&self) -> &str {
+        "files"
+    }
+
+    fn items(&self) -> &[PathBuf] {
+        self.index.files()
+    }
+
+    fn display(&self, path: &PathBuf) -> String {
+        path.strip_prefix("./")
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn boost(&self, path: &PathBuf) -> i64 {
+        let path: &Path = path.strip_prefix("./").unwrap_or(path);
+        self.boosts.get(path).copied().unwrap_or(0)
+    }
+
+    fn emphasis(&self, _item: &Self::Item, text: &str) -> usize {
+        file_name_start(text)
+    }
+
+    fn select(&self, path: &PathBuf) -> Vec<Cmd> {
+        vec![Cmd::LoadFile(path.clone())]
+    }
+
+    fn location(&self, path: &PathBuf) -> Option<Location> {
+        Some(Location {
+            path: path.clone(),
+            position: Point::default(),
+            text: String::new(),
+        })
+    }
+
+    fn preview(&self, path: &PathBuf) -> Option<PreviewTarget> {
+        Some(PreviewTarget {
+            path: path.clone(),
+            line: None,
+        })
+    }
+}
+
+/// Buffers that are loaded, with a `+` on modified ones
+pub struct BufferSource {
+    buffers: Vec<(BufferKey, String)>,
+}
+
+impl BufferSource {
+    pub fn new(state: &State) -> BufferSource {
+        let buffers = state
+            .buffers
+            .iter()
+            .map(|(key, buffer)| {
+                let name = match buffer.file() {
+                    Some(path) => path.to_string_lossy().into_owned(),
+                    None => buffer.name(),
+                };
+                let modified = if buffer.is_modified() { " [+]" } else { "" };
+                (key, format!("{}{}", name, modified))
+            })
+            .collect();
+        BufferSource { buffers }
+    }
+}
+
+impl Source for BufferSource {
+    type Item = (BufferKey, String);
+
+    fn prompt(&self) -> &str {
+        "buffers"
+    }
+
+    fn items(&self) -> &[(BufferKey, String)] {
+        &self.buffers
+    }
+
+    fn display(&self, (_, name): &(BufferKey, String)) -> String {
+        name.clone()
+    }
+
+    fn emphasis(&self, _item: &Self::Item, text: &str) -> usize {
+        file_name_start(text)
+    }
+
+    fn select(&self, (key, _): &(BufferKey, String)) -> Vec<Cmd> {
+        vec![Cmd::ShowBuffer(*key)]
+    }
+}
+
+/// Ex-commands, like a command palette
+pub struct CommandSource;
+
+impl Source for CommandSource {
+    type Item = Command;
+
+    fn prompt(&self) -> &str {
+        "commands"
+    }
+
+    fn items(&self) -> &[Command] {
+        COMMANDS
+    }
+
+    fn display(&self, command: &Command) -> String {
+        command.name().to_owned()
+    }
+
+    /// Ones that need an argument are left on the command line to finish
+    fn select(&self, command: &Command) -> Vec<Cmd> {
+        if command.takes_arg {
+            vec![
+                Cmd::ChangeMode(Mode::Command),
+                Cmd::InsertText(format!("{} ", command.name())),
+            ]
+        } else {
+            vec![Cmd::RunCommand(command.name().to_owned())]
+        }
+    }
+}
+
+/// Lines of the current buffer
+pub struct LineSource {
+    lines: Vec<(usize, String)>,
+}
+
+impl LineSource {
+    pub fn new(state: &State) -> LineSource {
+        let lines = state.buffers[state.current_buffer()]
+            .slice()
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let line: Cow<str> = line.into();
+                (index + 1, line.trim_end().to_owned())
+            })
+            .collect();
+        LineSource { lines }
+    }
+}
+
+impl Source for LineSource {
+    type Item = (usize, String);
+
+    fn prompt(&self) -> &str {
+        "lines"
+    }
+
+    fn items(&self) -> &[(usize, String)] {
+        &self.lines
+    }
+
+    fn display(&self, (number, line): &(usize, String)) -> String {
+        format!("{:>5} {}", number, line)
+    }
+
+    fn emphasis(&self, _item: &Self::Item, _text: &str) -> usize {
+        // Past the line number
+        6
+    }
+
+    fn select(&self, (number, _): &(usize, String)) -> Vec<Cmd> {
+        vec![Cmd::Jump(JumpType::Line(*number))]
+    }
+}
+
+/// Files that were opened before, in this session or earlier ones, by frecency
+pub struct RecentSource {
+    files: Vec<PathBuf>,
+}
+
+impl RecentSource {
+    pub fn new(state: &State) -> RecentSource {
+        RecentSource {
+            files: state.history.ranked(),
+        }
+    }
+}
+
+impl Source for RecentSource {
+    type Item = PathBuf;
+
+    fn prompt(&self) -> &str {
+        "recent"
+    }
+
+    fn items(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn display(&self, path: &PathBuf) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    fn emphasis(&self, _item: &Self::Item, text: &str) -> usize {
+        file_name_start(text)
+    }
+
+    fn select(&self, path: &PathBuf) -> Vec<Cmd> {
+        vec![Cmd::LoadFile(path.clone())]
+    }
+
+    fn preview(&self, path: &PathBuf) -> Option<PreviewTarget> {
+        Some(PreviewTarget {
+            path: path.clone(),
+            line: None,
+        })
+    }
+}
+
+/// Registers that hold something, picking one puts it after the cursor
+pub struct RegisterSource {
+    registers: Vec<(char, String)>,
+}
+
+impl RegisterSource {
+    pub fn new(state: &State) -> RegisterSource {
+        RegisterSource {
+            registers: state.registers.list(),
+        }
+    }
+}
+
+impl Source for RegisterSource {
+    type Item = (char, String);
+
+    fn prompt(&self) -> &str {
+        "registers"
+    }
+
+    fn items(&self) -> &[(char, String)] {
+        &self.registers
+    }
+
+    fn display(&self, (name, text): &(char, String)) -> String {
+        format!("\"{} {}", name, text.replace('\n', "^J"))
+    }
+
+    fn emphasis(&self, _item: &Self::Item, _text: &str) -> usize {
+        3
+    }
+
+    fn select(&self, (name, _): &(char, String)) -> Vec<Cmd> {
+        vec![Cmd::Paste(*name, true)]
+    }
+}
+
+/// The current buffer's marks, then the file marks
+pub struct MarkSource {
+    marks: Vec<(char, String)>,
+}
+
+impl MarkSource {
+    pub fn new(state: &State) -> MarkSource {
+        let current = state.current_buffer();
+        let describe = |key: BufferKey, name: char, point: Point| {
+            let buffer = &state.buffers[key];
+            let line: Cow<str> = buffer.slice().line(point.y as usize).into();
+            let text = if key == current {
+                line.trim().to_owned()
+            } else {
+                buffer.name()
+            };
+            (
+                name,
+                format!("{:>5}:{:<4} {}", point.y + 1, point.x + 1, text),
+            )
+        };
+        let mut marks: Vec<(char, String)> = state.buffers[current]
+            .marks()
+            .into_iter()
+            .filter(|(name, _)| name.is_ascii_lowercase())
+            .map(|(name, point)| describe(current, name, point))
+            .collect();
+        let mut file_marks: Vec<(char, BufferKey)> = state
+            .file_marks
+            .iter()
+            .map(|(name, key)| (*name, *key))
+            .collect();
+        file_marks.sort();
+        for (name, key) in file_marks {
+            if let Some(point) = state.buffers.get(key).and_then(|buffer| buffer.mark(name)) {
+                marks.push(describe(key, name, point));
+            }
+        }
+        MarkSource { marks }
+    }
+}
+
+impl Source for MarkSource {
+    type Item = (char, String);
+
+    fn prompt(&self) -> &str {
+        "marks"
+    }
+
+    fn items(&self) -> &[(char, String)] {
+        &self.marks
+    }
+
+    fn display(&self, (name, text): &(char, String)) -> String {
+        format!("{} {}", name, text)
+    }
+
+    fn select(&self, (name, _): &(char, String)) -> Vec<Cmd> {
+        vec![Cmd::JumpToMark(*name, true)]
+    }
+}
+
+/// Color schemes that come with syntect
+pub struct ThemeSource {
+    themes: Vec<String>,
+}
+
+impl ThemeSource {
+    pub fn new(state: &State) -> ThemeSource {
+        let mut themes: Vec<String> = state.config.theme_set.themes.keys().cloned().collect();
+        themes.sort();
+        ThemeSource { themes }
+    }
+}
+
+impl Source for ThemeSource {
+    type Item = String;
+
+    fn prompt(&self) -> &str {
+        "themes"
+    }
+
+    fn items(&self) -> &[String] {
+        &self.themes
+    }
+
+    fn display(&self, name: &String) -> String {
+        name.clone()
+    }
+
+    fn select(&self, name: &String) -> Vec<Cmd> {
+        vec![Cmd::SetTheme(name.clone())]
+    }
+}
+
+/// Text under the working directory, the query is a regex
+pub struct GrepSource {
+    grep: Grep,
+    /// Searches dotfiles too
+    hidden: bool,
+}
+
+impl GrepSource {
+    pub fn new(hidden: bool) -> GrepSource {
+        GrepSource {
+            grep: Grep::default(),
+            hidden,
+        }
+    }
+}
+
+/// `path:line:column: text`, same as grep tools and the quickfix list
+fn location_prefix(path: &std::path::Path, line: usize, column: usize) -> String {
+    format!("{}:{}:{}: ", path.display(), line, column)
+}
+
+impl Source for GrepSource {
+    type Item = GrepMatch;
+
+    fn prompt(&self) -> &str {
+        "grep"
+    }
+
+    fn items(&self) -> &[GrepMatch] {
+        self.grep.matches()
+    }
+
+    fn display(&self, grep_match: &GrepMatch) -> String {
+        let prefix = location_prefix(&grep_match.path, grep_match.line, grep_match.column);
+        prefix + &grep_match.text
+    }
+
+    fn emphasis(&self, grep_match: &GrepMatch, text: &str) -> usize {
+        text.chars().count() - grep_match.text.chars().count()
+    }
+
+    fn select(&self, grep_match: &GrepMatch) -> Vec<Cmd> {
+        self.location(grep_match)
+            .map(|location| location.cmds())
+            .unwrap_or_default()
+    }
+
+    fn location(&self, grep_match: &GrepMatch) -> Option<Location> {
+        Some(Location {
+            path: grep_match.path.clone(),
+            position: Point {
+                x: (grep_match.column - 1) as u16,
+                y: (grep_match.line - 1) as u16,
+            },
+            text: grep_match.text.clone(),
+        })
+    }
+
+    fn preview(&self, grep_match: &GrepMatch) -> Option<PreviewTarget> {
+        Some(PreviewTarget {
+            path: grep_match.path.clone(),
+            line: Some(grep_match.line - 1),
+        })
+    }
+
+    fn fuzzy(&self) -> bool {
+        false
+    }
+
+    fn query_changed(&mut self, query: &str, msg_sender: &EventLoopProxy<Msg>) {
+        self.grep.start(query, self.hidden, msg_sender.clone());
+    }
+
+    fn matched(&self, grep_match: &GrepMatch, text: &str) -> Vec<usize> {
+        let offset = self.emphasis(grep_match, text);
+        grep_match.matched.clone().map(|i| i + offset).collect()
+    }
+
+    fn update(&mut self, msg: GrepMsg) -> bool {
+        self.grep.update(msg)
+    }
+}
+
+/// What was last sent to the quickfix list
+pub struct QuickfixSource {
+    locations: Vec<(usize, Location)>,
+}
+
+impl QuickfixSource {
+    pub fn new(state: &State) -> QuickfixSource {
+        QuickfixSource {
+            locations: state
+                .quickfix
+                .locations()
+                .iter()
+                .cloned()
+                .enumerate()
+                .collect(),
+        }
+    }
+}
+
+impl Source for QuickfixSource {
+    type Item = (usize, Location);
+
+    fn prompt(&self) -> &str {
+        "quickfix"
+    }
+
+    fn items(&self) -> &[(usize, Location)] {
+        &self.locations
+    }
+
+    fn display(&self, (_, location): &(usize, Location)) -> String {
+        let Point { x, y } = location.position;
+        location_prefix(&location.path, y as usize + 1, x as usize + 1) + &location.text
+    }
+
+    fn emphasis(&self, (_, location): &(usize, Location), text: &str) -> usize {
+        text.chars().count() - location.text.chars().count()
+    }
+
+    fn select(&self, (index, _): &(usize, Location)) -> Vec<Cmd> {
+        vec![Cmd::GoToQuickfix(*index)]
+    }
+
+    fn location(&self, (_, location): &(usize, Location)) -> Option<Location> {
+        Some(location.clone())
+    }
+
+    fn preview(&self, (_, location): &(usize, Location)) -> Option<PreviewTarget> {
+        Some(PreviewTarget {
+            path: location.path.clone(),
+            line: Some(location.position.y as usize),
+        })
+    }
+}
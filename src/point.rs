@@ -1,9 +1,9 @@
-use crate::msg::{Direction, JumpType};
-use ropey::{RopeSlice, iter::Chars};
+use crate::msg::{CharSearch, Direction, JumpType};
+use ropey::{iter::Chars, RopeSlice};
 
 use flamer::flame;
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
 pub struct Point {
     pub x: u16,
     pub y: u16,
@@ -22,9 +22,7 @@ impl<'a> Iterator for RevChars<'a> {
 
 impl<'a> From<Chars<'a>> for RevChars<'a> {
     fn from(chars: Chars<'a>) -> RevChars<'a> {
-        RevChars {
-            chars
-        }
+        RevChars { chars }
     }
 }
 
@@ -87,8 +85,15 @@ impl Point {
         }
     }
 
-    fn search<T: Iterator<Item = char>>(&mut self, t: T, rope: &RopeSlice, direction: Direction, while_fn: fn(&char) -> bool) {
-        t.take_while(while_fn).for_each(|_| self.step(direction, rope));
+    fn search<T: Iterator<Item = char>>(
+        &mut self,
+        t: T,
+        rope: &RopeSlice,
+        direction: Direction,
+        while_fn: fn(&char) -> bool,
+    ) {
+        t.take_while(while_fn)
+            .for_each(|_| self.step(direction, rope));
     }
     fn step_while(&mut self, rope: &RopeSlice, direction: Direction, while_fn: fn(&char) -> bool) {
         let iter = rope.chars_at(self.index(rope));
@@ -98,9 +103,53 @@ impl Point {
             Direction::Up | Direction::Down => unimplemented!(),
         }
     }
-    pub fn jump(&mut self, jump_type: JumpType, rope: &RopeSlice, line_count: usize) {
+    /// Moves to the `count`th match, or stays put if there aren't that many
+    fn find(&mut self, search: &CharSearch, rope: &RopeSlice, count: usize) {
+        let target = search.target.chars().collect::<Vec<_>>();
+        let (start, end) = if search.multi_line {
+            (0, rope.len_chars())
+        } else {
+            let start = rope.line_to_char(self.y as usize);
+            (start, start + rope.line(self.y as usize).len_chars())
+        };
+        let matches_at = |index: usize| {
+            index + target.len() <= end
+                && target
+                    .iter()
+                    .enumerate()
+                    .all(|(offset, c)| rope.char(index + offset) == *c)
+        };
+        let index = self.index(rope);
+        // A repeated `t` would find the match it's already sitting in front of
+        let skip = if search.till && search.repeat { 1 } else { 0 };
+        let found = if search.forward {
+            (index + 1 + skip..end)
+                .filter(|i| matches_at(*i))
+                .nth(count - 1)
+        } else {
+            (start..index.saturating_sub(skip))
+                .rev()
+                .filter(|i| matches_at(*i))
+                .nth(count - 1)
+        };
+        if let Some(found) = found {
+            let found = match (search.till, search.forward) {
+                (false, _) => found,
+                (true, true) => found - 1,
+                (true, false) => found + 1,
+            };
+            self.step_to_index(found, rope);
+        }
+    }
+
+    /// `count` is how many times to repeat the jump, `line_count` is the height of a page
+    pub fn jump(&mut self, jump_type: JumpType, rope: &RopeSlice, line_count: usize, count: usize) {
         match jump_type {
             JumpType::EndOfLine => {
+                // Like vim, a count goes that many lines down
+                for _ in 1..count {
+                    self.step(Direction::Down, rope);
+                }
                 let line = rope.line(self.y as usize);
                 self.x = line.len_chars() as u16
                     - if self.y as usize == rope.len_lines() - 1 {
@@ -122,37 +171,151 @@ impl Point {
                 self.y = rope.len_lines() as u16 - 1;
                 self.x = 0;
             }
+            JumpType::Line(line) => {
+                self.y = line.saturating_sub(1).min(rope.len_lines() - 1) as u16;
+                self.x = 0;
+            }
             JumpType::NextWord => {
-                self.step_while(rope, Direction::Right, |c| c.is_alphanumeric());
-                self.step_while(rope, Direction::Right, |c| !c.is_alphanumeric());
+                for _ in 0..count {
+                    self.step_while(rope, Direction::Right, |c| c.is_alphanumeric());
+                    self.step_while(rope, Direction::Right, |c| !c.is_alphanumeric());
+                }
             }
             JumpType::EndOfWord => {
-                if rope.char(self.index(rope)).is_alphanumeric() && !rope.char(self.index(rope) + 1).is_alphanumeric() { // TODO: Handle edges of buffer
-                    self.step(Direction::Right, rope);
+                for _ in 0..count {
+                    let index = self.index(rope);
+                    if is_word_char(rope, index) && !is_word_char(rope, index + 1) {
+                        self.step(Direction::Right, rope);
+                    }
+                    self.step_while(rope, Direction::Right, |c| !c.is_alphanumeric());
+                    self.step_while(rope, Direction::Right, |c| c.is_alphanumeric());
+                    self.step(Direction::Left, rope);
                 }
-                self.step_while(rope, Direction::Right, |c| !c.is_alphanumeric());
-                self.step_while(rope, Direction::Right, |c| c.is_alphanumeric());
-                self.step(Direction::Left, rope);
             }
             JumpType::PrevWord => {
-                if rope.char(self.index(rope)).is_alphanumeric() && !rope.char(self.index(rope) - 1).is_alphanumeric() { // TODO: Handle edges of buffer
-                    self.step(Direction::Left, rope);
-                    self.step_while(rope, Direction::Left, |c| !c.is_alphanumeric())
+                for _ in 0..count {
+                    let index = self.index(rope);
+                    if is_word_char(rope, index) && index > 0 && !is_word_char(rope, index - 1) {
+                        self.step(Direction::Left, rope);
+                        self.step_while(rope, Direction::Left, |c| !c.is_alphanumeric())
+                    }
+                    self.step_while(rope, Direction::Left, |c| c.is_alphanumeric())
                 }
-                self.step_while(rope, Direction::Left, |c| c.is_alphanumeric())
+            }
+            JumpType::Find(search) => self.find(&search, rope, count.max(1)),
+            // Turned into a `Find` with the last search before it gets here
+            JumpType::RepeatFind(_) => {}
+            // Needs the syntax to skip strings and comments, so the buffer does it
+            JumpType::MatchingBracket => {}
+            JumpType::Position(point) => {
+                self.y = point.y.min(rope.len_lines() as u16 - 1);
+                self.x = point.x;
+                self.prevent_runoff(rope);
             }
             JumpType::PageForward => {
-                for _ in 0..line_count {
-                    self.step(Direction::Down, rope);
-                }
+                let last_line = rope.len_lines().saturating_sub(1);
+                let line = (self.y as usize).saturating_add(line_count.saturating_mul(count));
+                self.y = line.min(last_line) as u16;
                 self.prevent_runoff(rope);
             }
             JumpType::PageBackward => {
-                for _ in 0..line_count {
-                    self.step(Direction::Up, rope);
-                }
+                let line = (self.y as usize).saturating_sub(line_count.saturating_mul(count));
+                self.y = line as u16;
                 self.prevent_runoff(rope);
             }
         }
     }
 }
+
+/// False past either end of the text
+fn is_word_char(rope: &RopeSlice, index: usize) -> bool {
+    index < rope.len_chars() && rope.char(index).is_alphanumeric()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ropey::Rope;
+
+    fn search(target: &str, forward: bool, till: bool) -> CharSearch {
+        CharSearch {
+            target: target.to_owned(),
+            forward,
+            till,
+            multi_line: false,
+            repeat: false,
+        }
+    }
+
+    #[test]
+    fn find_forward() {
+        let rope = Rope::from_str("a,b,c,d\n,e");
+        let mut point = Point::default();
+        point.find(&search(",", true, false), &rope.slice(..), 2);
+        assert_eq!(point, Point { x: 3, y: 0 });
+        point.find(&search(",", true, true), &rope.slice(..), 1);
+        assert_eq!(point, Point { x: 4, y: 0 });
+    }
+
+    #[test]
+    fn find_backward() {
+        let rope = Rope::from_str("a,b,c,d");
+        let mut point = Point { x: 6, y: 0 };
+        point.find(&search(",", false, false), &rope.slice(..), 1);
+        assert_eq!(point, Point { x: 5, y: 0 });
+        point.find(&search("a", false, true), &rope.slice(..), 1);
+        assert_eq!(point, Point { x: 1, y: 0 });
+    }
+
+    #[test]
+    fn find_stays_on_its_line() {
+        let rope = Rope::from_str("ab\nab");
+        let mut point = Point::default();
+        point.find(&search("a", true, false), &rope.slice(..), 1);
+        assert_eq!(point, Point::default());
+        let mut multi_line = search("a", true, false);
+        multi_line.multi_line = true;
+        point.find(&multi_line, &rope.slice(..), 1);
+        assert_eq!(point, Point { x: 0, y: 1 });
+    }
+
+    #[test]
+    fn pages_stop_at_the_ends() {
+        let rope = Rope::from_str(&vec!["line"; 11].join("\n"));
+        let mut point = Point { x: 3, y: 2 };
+        point.jump(JumpType::PageForward, &rope.slice(..), 4, 3);
+        assert_eq!(point, Point { x: 3, y: 10 });
+        point.jump(JumpType::PageBackward, &rope.slice(..), 4, 1);
+        assert_eq!(point, Point { x: 3, y: 6 });
+        point.jump(
+            JumpType::PageBackward,
+            &rope.slice(..),
+            4,
+            usize::max_value(),
+        );
+        assert_eq!(point, Point { x: 3, y: 0 });
+    }
+
+    #[test]
+    fn repeated_till_moves_on() {
+        let rope = Rope::from_str("a,b,c");
+        let mut point = Point::default();
+        let mut till = search(",", true, true);
+        point.find(&till, &rope.slice(..), 1);
+        assert_eq!(point, Point { x: 0, y: 0 });
+        till.repeat = true;
+        point.find(&till, &rope.slice(..), 1);
+        assert_eq!(point, Point { x: 2, y: 0 });
+    }
+
+    #[test]
+    fn word_motions_stop_at_the_ends() {
+        let rope = Rope::from_str("ab cd");
+        let mut point = Point { x: 4, y: 0 };
+        point.jump(JumpType::EndOfWord, &rope.slice(..), 1, 1);
+        assert_eq!(point, Point { x: 4, y: 0 });
+        let mut point = Point::default();
+        point.jump(JumpType::PrevWord, &rope.slice(..), 1, 1);
+        assert_eq!(point, Point::default());
+    }
+}
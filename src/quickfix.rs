@@ -0,0 +1,45 @@
+use crate::msg::Location;
+
+/// Places exported from a picker, walked with `:cnext` and `:cprevious`
+#[derive(Debug, Clone, Default)]
+pub struct Quickfix {
+    locations: Vec<Location>,
+    /// Nothing until the first one is visited
+    current: Option<usize>,
+}
+
+impl Quickfix {
+    pub fn set(&mut self, locations: Vec<Location>) {
+        self.locations = locations;
+        self.current = None;
+    }
+
+    pub fn locations(&self) -> &[Location] {
+        &self.locations
+    }
+
+    pub fn select(&mut self, index: usize) -> Option<&Location> {
+        let location = self.locations.get(index)?;
+        self.current = Some(index);
+        Some(location)
+    }
+
+    /// `None` past the last one, like vim
+    pub fn next(&mut self, count: usize) -> Option<&Location> {
+        let index = match self.current {
+            Some(current) if current + 1 >= self.locations.len() => return None,
+            Some(current) => (current + count).min(self.locations.len() - 1),
+            None => count
+                .saturating_sub(1)
+                .min(self.locations.len().checked_sub(1)?),
+        };
+        self.select(index)
+    }
+
+    pub fn prev(&mut self, count: usize) -> Option<&Location> {
+        match self.current {
+            Some(current) if current > 0 => self.select(current.saturating_sub(count)),
+            _ => None,
+        }
+    }
+}
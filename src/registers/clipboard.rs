@@ -0,0 +1,147 @@
+use crate::error::Error;
+
+use anyhow::Result;
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Selection {
+    /// What Ctrl-C/Ctrl-V use in other applications, the `+` register
+    Clipboard,
+    /// Whatever was last selected with the mouse, the `*` register
+    Primary,
+}
+
+/// Somewhere to keep text that other applications can see
+pub trait ClipboardProvider {
+    fn get(&mut self, selection: Selection) -> Result<String>;
+    fn set(&mut self, selection: Selection, text: &str) -> Result<()>;
+}
+
+/// Only visible inside the editor, for when there is no display server
+#[derive(Debug, Default)]
+pub struct MemoryProvider {
+    clipboard: String,
+    primary: String,
+}
+
+impl ClipboardProvider for MemoryProvider {
+    fn get(&mut self, selection: Selection) -> Result<String> {
+        Ok(match selection {
+            Selection::Clipboard => self.clipboard.clone(),
+            Selection::Primary => self.primary.clone(),
+        })
+    }
+
+    fn set(&mut self, selection: Selection, text: &str) -> Result<()> {
+        match selection {
+            Selection::Clipboard => self.clipboard = text.to_owned(),
+            Selection::Primary => self.primary = text.to_owned(),
+        }
+        Ok(())
+    }
+}
+
+/// Shells out to the usual clipboard tools, same as vim and friends do on linux
+#[derive(Debug)]
+pub struct CommandProvider {
+    /// Program and arguments, clipboard first then primary
+    copy: [&'static [&'static str]; 2],
+    paste: [&'static [&'static str]; 2],
+}
+
+const WAYLAND: CommandProvider = CommandProvider {
+    copy: [
+        &["wl-copy", "--type", "text/plain"],
+        &["wl-copy", "--primary", "--type", "text/plain"],
+    ],
+    paste: [
+        &["wl-paste", "--no-newline"],
+        &["wl-paste", "--no-newline", "--primary"],
+    ],
+};
+
+const XCLIP: CommandProvider = CommandProvider {
+    copy: [
+        &["xclip", "-i", "-selection", "clipboard"],
+        &["xclip", "-i", "-selection", "primary"],
+    ],
+    paste: [
+        &["xclip", "-o", "-selection", "clipboard"],
+        &["xclip", "-o", "-selection", "primary"],
+    ],
+};
+
+const XSEL: CommandProvider = CommandProvider {
+    copy: [&["xsel", "-i", "-b"], &["xsel", "-i", "-p"]],
+    paste: [&["xsel", "-o", "-b"], &["xsel", "-o", "-p"]],
+};
+
+fn has_command(name: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| Path::new(&dir).join(name).is_file()))
+        .unwrap_or(false)
+}
+
+fn index(selection: Selection) -> usize {
+    match selection {
+        Selection::Clipboard => 0,
+        Selection::Primary => 1,
+    }
+}
+
+impl ClipboardProvider for CommandProvider {
+    fn get(&mut self, selection: Selection) -> Result<String> {
+        let args = self.paste[index(selection)];
+        let output = Command::new(args[0])
+            .args(&args[1..])
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|err| Error::Clipboard(format!("{}: {}", args[0], err)))?;
+        // An empty selection is reported as a failure by most of these
+        if !output.status.success() {
+            return Ok(String::new());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn set(&mut self, selection: Selection, text: &str) -> Result<()> {
+        let args = self.copy[index(selection)];
+        let mut child = Command::new(args[0])
+            .args(&args[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| Error::Clipboard(format!("{}: {}", args[0], err)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        // They all fork to keep serving the selection, so this doesn't block
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::Clipboard(format!("{} exited with {}", args[0], status)).anyhow())
+        }
+    }
+}
+
+/// Picks a provider that works with the current display server
+pub fn detect() -> Box<dyn ClipboardProvider> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let x11 = std::env::var_os("DISPLAY").is_some();
+    if wayland && has_command("wl-copy") && has_command("wl-paste") {
+        Box::new(WAYLAND)
+    } else if x11 && has_command("xclip") {
+        Box::new(XCLIP)
+    } else if x11 && has_command("xsel") {
+        Box::new(XSEL)
+    } else {
+        Box::new(MemoryProvider::default())
+    }
+}
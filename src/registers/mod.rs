@@ -0,0 +1,116 @@
+use anyhow::Result;
+
+use std::collections::HashMap;
+
+mod clipboard;
+pub use clipboard::{detect, ClipboardProvider, MemoryProvider, Selection};
+
+/// The register yanks and pastes use when none is given
+pub const UNNAMED: char = '"';
+
+/// Holds yanked text. `+` and `*` go straight to the system clipboard and primary
+/// selection, everything else lives in memory.
+pub struct Registers {
+    values: HashMap<char, String>,
+    clipboard: Box<dyn ClipboardProvider>,
+}
+
+impl Registers {
+    pub fn new(clipboard: Box<dyn ClipboardProvider>) -> Registers {
+        Registers {
+            values: HashMap::new(),
+            clipboard,
+        }
+    }
+
+    pub fn get(&mut self, register: char) -> Result<String> {
+        match register {
+            '+' => self.clipboard.get(Selection::Clipboard),
+            '*' => self.clipboard.get(Selection::Primary),
+            register => Ok(self
+                .values
+                .get(&register.to_ascii_lowercase())
+                .cloned()
+                .unwrap_or_default()),
+        }
+    }
+
+    /// Everything held in memory, sorted by name. The clipboard isn't asked.
+    pub fn list(&self) -> Vec<(char, String)> {
+        let mut registers: Vec<(char, String)> = self
+            .values
+            .iter()
+            .map(|(name, text)| (*name, text.clone()))
+            .collect();
+        registers.sort();
+        registers
+    }
+
+    /// Like vim, the unnamed register always gets a copy too and an
+    /// uppercase name appends to the lowercase register
+    pub fn set(&mut self, register: char, text: String) -> Result<()> {
+        let text = match register {
+            '+' => {
+                self.clipboard.set(Selection::Clipboard, &text)?;
+                text
+            }
+            '*' => {
+                self.clipboard.set(Selection::Primary, &text)?;
+                text
+            }
+            register if register.is_ascii_uppercase() => {
+                let value = self
+                    .values
+                    .entry(register.to_ascii_lowercase())
+                    .or_default();
+                value.push_str(&text);
+                value.clone()
+            }
+            register => {
+                self.values.insert(register, text.clone());
+                text
+            }
+        };
+        self.values.insert(UNNAMED, text);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registers() -> Registers {
+        Registers::new(Box::new(MemoryProvider::default()))
+    }
+
+    #[test]
+    fn yank_and_paste() {
+        let mut registers = registers();
+        registers.set('a', "text".to_owned()).unwrap();
+        assert_eq!(registers.get('a').unwrap(), "text");
+        assert_eq!(registers.get(UNNAMED).unwrap(), "text");
+        assert_eq!(registers.get('b').unwrap(), "");
+    }
+
+    #[test]
+    fn uppercase_appends() {
+        let mut registers = registers();
+        registers.set('a', "one\n".to_owned()).unwrap();
+        registers.set('A', "two\n".to_owned()).unwrap();
+        assert_eq!(registers.get('a').unwrap(), "one\ntwo\n");
+        assert_eq!(registers.get('A').unwrap(), "one\ntwo\n");
+        assert_eq!(registers.get(UNNAMED).unwrap(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn clipboard_goes_through_the_provider() {
+        let mut registers = registers();
+        registers.set('+', "copied".to_owned()).unwrap();
+        registers.set('*', "selected".to_owned()).unwrap();
+        assert_eq!(registers.get('+').unwrap(), "copied");
+        assert_eq!(registers.get('*').unwrap(), "selected");
+        assert_eq!(registers.get(UNNAMED).unwrap(), "selected");
+        assert!(registers.list().iter().all(|(name, _)| *name == UNNAMED));
+    }
+}
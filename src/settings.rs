@@ -0,0 +1,82 @@
+use crate::error::Error;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum LineNumbers {
+    Off,
+    Absolute,
+    Relative,
+    /// Relative numbers with the absolute one on the cursor line
+    Hybrid,
+}
+
+/// Options that can be changed at runtime with `:set`
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub number: bool,
+    pub relative_number: bool,
+    /// Milliseconds to wait for the next key of a mapping
+    pub timeout_len: u64,
+    /// Lines moved per scroll wheel tick, 0 turns wheel scrolling off
+    pub mouse_scroll: u64,
+    /// Colors brackets by how deeply they are nested
+    pub rainbow: bool,
+    /// Lists dotfiles in Skim mode
+    pub hidden_files: bool,
+    /// Shows the file under the picker's selection beside the list
+    pub preview: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            number: true,
+            relative_number: false,
+            timeout_len: 1000,
+            mouse_scroll: 3,
+            rainbow: false,
+            hidden_files: false,
+            preview: true,
+        }
+    }
+}
+
+impl Settings {
+    pub fn line_numbers(&self) -> LineNumbers {
+        match (self.number, self.relative_number) {
+            (false, false) => LineNumbers::Off,
+            (true, false) => LineNumbers::Absolute,
+            (false, true) => LineNumbers::Relative,
+            (true, true) => LineNumbers::Hybrid,
+        }
+    }
+
+    /// Applies a single `:set` argument like `number`, `norelativenumber` or `timeoutlen=500`
+    pub fn set(&mut self, arg: &str) -> Result<(), Error> {
+        if let Some(index) = arg.find('=') {
+            let (name, value) = (&arg[..index], &arg[index + 1..]);
+            let value = value
+                .parse::<u64>()
+                .map_err(|_| Error::InvalidArg(arg.to_owned()))?;
+            match name {
+                "timeoutlen" | "tm" => self.timeout_len = value,
+                "mousescroll" => self.mouse_scroll = value,
+                _ => return Err(Error::UnknownOption(arg.to_owned())),
+            }
+            return Ok(());
+        }
+        let (name, value) = if arg.starts_with("no") {
+            (&arg[2..], false)
+        } else {
+            (arg, true)
+        };
+        match name {
+            "number" | "nu" => self.number = value,
+            "relativenumber" | "rnu" => self.relative_number = value,
+            "rainbow" => self.rainbow = value,
+            "hiddenfiles" => self.hidden_files = value,
+            "preview" => self.preview = value,
+            _ => return Err(Error::UnknownOption(arg.to_owned())),
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,31 @@
 use crate::{
-    buffer::{get_visible_lines, Buffer, BufferKey},
+    buffer::{Buffer, BufferKey},
     command::CommandBuffer,
+    config_file::ConfigFile,
+    error::Error,
+    history::FileHistory,
+    input::Keymap,
+    messages::{MessageLog, Severity},
     mode::Mode,
-    skim_buffer::SkimBuffer,
+    msg::CharSearch,
+    picker::Pickers,
+    quickfix::Quickfix,
+    registers::{self, Registers},
+    settings::Settings,
+    statusline::{StatusLine, DEFAULT_FORMAT},
+    swap::SwapFiles,
+    view::{Gutter, MarkSigns, Tab, View, ViewKey},
 };
 
-use syntect::{highlighting::Theme, parsing::SyntaxSet};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use syntect::{
+    highlighting::{Theme, ThemeSet},
+    parsing::SyntaxSet,
+};
 
 use anyhow::{anyhow, Result};
 use slotmap::{SecondaryMap, SlotMap};
@@ -13,19 +33,34 @@ use slotmap::{SecondaryMap, SlotMap};
 /// Passed around configurations to make things easier
 pub struct Config {
     pub theme: Theme,
+    pub theme_set: ThemeSet,
     pub syntax_set: SyntaxSet,
 }
 
 pub struct State {
     pub buffer_keys: SlotMap<BufferKey, ()>,
     pub buffers: SecondaryMap<BufferKey, Buffer>,
-    pub current_buffer: BufferKey,
+    pub views: SlotMap<ViewKey, View>,
+    pub tabs: Vec<Tab>,
+    pub current_tab: usize,
     pub mode: Mode,
     pub command_buffer: CommandBuffer,
-    pub status: Option<String>,
-    pub skim_buffer: SkimBuffer,
+    pub messages: MessageLog,
+    pub pickers: Pickers,
     pub config: Config,
-    pub line_count: usize,
+    pub settings: Settings,
+    pub gutter: Gutter,
+    pub status_line: StatusLine,
+    pub keymap: Keymap,
+    pub registers: Registers,
+    /// For `;` and `,`
+    pub last_find: Option<CharSearch>,
+    /// Which buffer holds each `A`-`Z` mark, the position lives in the buffer
+    pub file_marks: HashMap<char, BufferKey>,
+    /// Every file opened, for ranking the pickers
+    pub history: FileHistory,
+    pub quickfix: Quickfix,
+    pub swap: SwapFiles,
 }
 
 const SYNTAXES: &[&str] = &[
@@ -47,29 +82,141 @@ fn build_syntax_set() -> Result<SyntaxSet> {
 }
 
 impl State {
-    pub fn new() -> Result<State> {
+    pub fn new(config_file: ConfigFile) -> Result<State> {
+        let mut keymap = Keymap::new(config_file.leader.as_ref().map(String::as_str))?;
+        for (mode_name, mappings) in config_file.keymaps.iter() {
+            let mode =
+                Mode::from_name(mode_name).ok_or_else(|| Error::UnknownMode(mode_name.clone()))?;
+            for (lhs, rhs) in mappings.iter() {
+                // Non-recursive, so they always mean what they say
+                keymap.map(mode, lhs, rhs, false)?;
+            }
+        }
+        let mut settings = Settings::default();
+        for arg in config_file.set.iter() {
+            settings.set(arg)?;
+        }
+        let theme_set = ThemeSet::load_defaults();
+        let theme = theme_set.themes["base16-ocean.dark"].clone();
         let mut buffer_keys = SlotMap::new();
         let current_buffer = buffer_keys.insert(());
         let mut buffers = SecondaryMap::new();
         buffers.insert(current_buffer, Buffer::new()?);
+        let mut views = SlotMap::new();
+        let current_view = views.insert(View::new(current_buffer));
+        let mut gutter = Gutter::default();
+        gutter.register(Box::new(MarkSigns));
         Ok(State {
             buffers,
             buffer_keys,
-            current_buffer,
+            views,
+            tabs: vec![Tab::new(current_view)],
+            current_tab: 0,
             mode: Mode::Normal,
             command_buffer: CommandBuffer::default(),
-            status: None,
-            skim_buffer: SkimBuffer::default(),
+            pickers: Pickers::default(),
+            messages: MessageLog::new(&theme),
             config: Config {
-                theme: syntect::highlighting::ThemeSet::load_defaults().themes["base16-ocean.dark"]
-                    .clone(),
+                theme,
+                theme_set,
                 syntax_set: build_syntax_set()?,
             },
-            line_count: 0,
+            settings,
+            gutter,
+            status_line: StatusLine::parse(
+                config_file
+                    .statusline
+                    .as_ref()
+                    .map(String::as_str)
+                    .unwrap_or(DEFAULT_FORMAT),
+            )?,
+            keymap,
+            registers: Registers::new(registers::detect()),
+            last_find: None,
+            file_marks: HashMap::new(),
+            // A broken history isn't worth refusing to start over
+            history: FileHistory::load().unwrap_or_default(),
+            quickfix: Quickfix::default(),
+            swap: SwapFiles::default(),
         })
     }
 
-    pub fn update_from_ui(&mut self, ui: &imgui::Ui) {
-        self.line_count = get_visible_lines(ui);
+    /// The buffer that already has `path` loaded, which has to be canonical
+    pub fn find_buffer(&self, path: &Path) -> Option<BufferKey> {
+        self.buffers
+            .iter()
+            .find(|(_, buffer)| {
+                buffer
+                    .file()
+                    .and_then(|file| std::fs::canonicalize(file).ok())
+                    .map_or(false, |file| file == path)
+            })
+            .map(|(key, _)| key)
+    }
+
+    /// Counts a visit to `path`, which has to be canonical
+    pub fn remember_file(&mut self, path: PathBuf) {
+        self.history.visit(path);
+        if let Err(err) = self.history.save() {
+            self.messages.push(
+                Severity::Warn,
+                format!("Couldn't save file history: {}", err),
+            );
+        }
+    }
+
+    /// Due every few seconds while there are unsaved edits
+    pub fn write_swap_files(&mut self) {
+        if let Err(err) = self.swap.write() {
+            self.messages
+                .push(Severity::Warn, format!("Couldn't write swap file: {}", err));
+        }
+    }
+
+    /// Whatever comes next gets undone separately from the edits so far
+    pub fn commit_undo(&mut self) {
+        for buffer in self.buffers.values_mut() {
+            buffer.commit_undo();
+        }
+    }
+
+    pub fn tab(&self) -> &Tab {
+        &self.tabs[self.current_tab]
+    }
+
+    pub fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.current_tab]
+    }
+
+    pub fn current_view(&self) -> ViewKey {
+        self.tab().current_view
+    }
+
+    pub fn current_buffer(&self) -> BufferKey {
+        self.views[self.current_view()].buffer
+    }
+
+    /// Opens a tab with an empty buffer right after the current one
+    pub fn new_tab(&mut self) -> Result<()> {
+        let buffer_key = self.buffer_keys.insert(());
+        self.buffers.insert(buffer_key, Buffer::new()?);
+        let view_key = self.views.insert(View::new(buffer_key));
+        self.current_tab += 1;
+        self.tabs.insert(self.current_tab, Tab::new(view_key));
+        Ok(())
+    }
+
+    pub fn close_tab(&mut self) -> Result<()> {
+        if self.tabs.len() == 1 {
+            return Err(Error::LastTab.anyhow());
+        }
+        let tab = self.tabs.remove(self.current_tab);
+        for view_key in tab.layout.views() {
+            self.views.remove(view_key);
+        }
+        if self.current_tab >= self.tabs.len() {
+            self.current_tab = self.tabs.len() - 1;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,193 @@
+use crate::{buffer::Buffer, cursor::Cursor, error::Error, mode::Mode};
+
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_FORMAT: &str =
+    "{file}{modified}{=}{pending} {recording} {branch} {filetype} {encoding} {lineending} {line}:{col} {percent} {mode}";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Mode,
+    File,
+    Modified,
+    FileType,
+    Encoding,
+    LineEnding,
+    Line,
+    Col,
+    Percent,
+    Pending,
+    Recording,
+    Branch,
+}
+
+impl Segment {
+    fn from_name(name: &str) -> Result<Segment, Error> {
+        Ok(match name {
+            "mode" => Segment::Mode,
+            "file" => Segment::File,
+            "modified" => Segment::Modified,
+            "filetype" => Segment::FileType,
+            "encoding" => Segment::Encoding,
+            "lineending" => Segment::LineEnding,
+            "line" => Segment::Line,
+            "col" => Segment::Col,
+            "percent" => Segment::Percent,
+            "pending" => Segment::Pending,
+            "recording" => Segment::Recording,
+            "branch" => Segment::Branch,
+            _ => return Err(Error::UnknownSegment(name.to_owned())),
+        })
+    }
+}
+
+/// Everything the segments are built from
+pub struct StatusContext<'a> {
+    pub mode: Mode,
+    pub buffer: &'a Buffer,
+    pub cursor: &'a Cursor,
+    pub pending: &'a str,
+    pub recording: Option<char>,
+}
+
+/// Segment based status line built from a format string like
+/// `{file}{modified}{=}{line}:{col}`. Anything after `{=}` is right aligned.
+pub struct StatusLine {
+    left: Vec<Segment>,
+    right: Vec<Segment>,
+    branch: Option<String>,
+}
+
+fn parse_segments(format: &str) -> Result<Vec<Segment>, Error> {
+    let mut segments = Vec::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            segments.push(Segment::Text(rest[..start].to_owned()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| Error::UnknownSegment(rest[start..].to_owned()))?;
+        segments.push(Segment::from_name(&rest[start + 1..start + end])?);
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_owned()));
+    }
+    Ok(segments)
+}
+
+/// Reads the checked out branch out of the closest `.git/HEAD`
+fn git_branch(dir: &Path) -> Option<String> {
+    let head = dir
+        .ancestors()
+        .map(|ancestor| ancestor.join(".git").join("HEAD"))
+        .find(|head| head.is_file())?;
+    let contents = std::fs::read_to_string(head).ok()?;
+    let contents = contents.trim();
+    if contents.starts_with("ref: refs/heads/") {
+        Some(contents["ref: refs/heads/".len()..].to_owned())
+    } else {
+        // Detached head, just show the short hash
+        Some(contents.chars().take(7).collect())
+    }
+}
+
+impl StatusLine {
+    pub fn parse(format: &str) -> Result<StatusLine, Error> {
+        let (left, right) = match format.find("{=}") {
+            Some(index) => (&format[..index], &format[index + 3..]),
+            None => (format, ""),
+        };
+        let mut status_line = StatusLine {
+            left: parse_segments(left)?,
+            right: parse_segments(right)?,
+            branch: None,
+        };
+        status_line.refresh_branch(None);
+        Ok(status_line)
+    }
+
+    /// Looks up the branch next to `file`, or the working directory when there is no file
+    pub fn refresh_branch(&mut self, file: Option<&Path>) {
+        let dir = file
+            .and_then(|file| file.parent())
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let dir = dir.canonicalize().unwrap_or(dir);
+        self.branch = git_branch(&dir);
+    }
+
+    fn segment_text(&self, segment: &Segment, context: &StatusContext) -> String {
+        let buffer = context.buffer;
+        match segment {
+            Segment::Text(text) => text.clone(),
+            Segment::Mode => context.mode.as_str().to_owned(),
+            Segment::File => buffer
+                .file()
+                .map(|file| file.to_string_lossy().into_owned())
+                .unwrap_or_else(|| buffer.name()),
+            Segment::Modified if buffer.is_modified() => " [+]".to_owned(),
+            Segment::Modified => String::new(),
+            Segment::FileType => buffer.filetype().unwrap_or("").to_owned(),
+            Segment::Encoding => "utf-8".to_owned(),
+            Segment::LineEnding => buffer.line_ending().to_owned(),
+            Segment::Line => format!("{}", context.cursor.row() + 1),
+            Segment::Col => format!("{}", context.cursor.col() + 1),
+            Segment::Percent => format!(
+                "{}%",
+                (context.cursor.row() + 1) * 100 / buffer.len_lines().max(1)
+            ),
+            Segment::Pending => context.pending.to_owned(),
+            Segment::Recording => context
+                .recording
+                .map(|register| format!("recording @{}", register))
+                .unwrap_or_default(),
+            Segment::Branch => self.branch.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Text between two segments only shows up once both sides have something,
+    /// so an empty segment doesn't leave a gap behind
+    fn build(&self, segments: &[Segment], context: &StatusContext) -> String {
+        let mut text = String::new();
+        // Since the last segment, whether or not it was empty
+        let mut separator = String::new();
+        let mut leading = true;
+        for segment in segments {
+            if let Segment::Text(literal) = segment {
+                separator.push_str(literal);
+                continue;
+            }
+            let value = self.segment_text(segment, context);
+            if !value.is_empty() {
+                if leading || !text.is_empty() {
+                    text.push_str(&separator);
+                }
+                text.push_str(&value);
+            }
+            separator.clear();
+            leading = false;
+        }
+        if leading || !text.is_empty() {
+            text.push_str(&separator);
+        }
+        text
+    }
+
+    /// `show_left` is false when something else (command line, status message) owns the left side
+    pub fn render(&self, ui: &imgui::Ui, context: &StatusContext, show_left: bool) {
+        if show_left {
+            let left = imgui::ImString::new(self.build(&self.left, context));
+            ui.set_cursor_pos([10., 0.]);
+            ui.text(left);
+        }
+        let right = imgui::ImString::new(self.build(&self.right, context));
+        let [width, _height] = ui.window_size();
+        let [text_width, _text_height] = ui.calc_text_size(&right, false, width);
+        ui.set_cursor_pos([width - text_width - 15., 0.]);
+        ui.text(right);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    buffer::{Buffer, BufferKey},
+    config_file::{flatten_path, state_dir},
+};
+
+use anyhow::Result;
+use ropey::Rope;
+use slotmap::SecondaryMap;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, TryLockError},
+    time::{Duration, Instant},
+};
+
+/// How long unsaved edits can go without being written to their swap file
+const SWAP_INTERVAL: Duration = Duration::from_secs(4);
+
+fn swap_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("swap"))
+}
+
+/// Where edits to `file`, which has to be canonical, are kept until it's saved
+pub fn swap_path(file: &Path) -> Option<PathBuf> {
+    swap_dir().map(|dir| dir.join(format!("{}.swp", flatten_path(file))))
+}
+
+/// Unnamed buffers can't be recovered by opening them, but a crash still shouldn't lose them
+fn buffer_swap_path(key: BufferKey, buffer: &Buffer) -> Option<PathBuf> {
+    match buffer.file() {
+        Some(file) => swap_path(&std::fs::canonicalize(file).unwrap_or_else(|_| file.into())),
+        None => {
+            swap_dir().map(|dir| dir.join(format!("unnamed-{}-{:?}.swp", std::process::id(), key)))
+        }
+    }
+}
+
+/// The text of a modified buffer as of its last edit
+struct Snapshot {
+    path: PathBuf,
+    rope: Rope,
+    version: u64,
+    /// On disk in `path`
+    written: bool,
+}
+
+impl Snapshot {
+    fn write(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.rope
+            .write_to(std::io::BufWriter::new(std::fs::File::create(&self.path)?))?;
+        Ok(())
+    }
+}
+
+type Snapshots = Arc<Mutex<HashMap<BufferKey, Snapshot>>>;
+
+/// Writes unsaved edits to the swap directory every few seconds, and all of them
+/// at once if the editor panics
+pub struct SwapFiles {
+    /// Shared with the panic hook
+    snapshots: Snapshots,
+    last_write: Instant,
+}
+
+impl Default for SwapFiles {
+    fn default() -> SwapFiles {
+        SwapFiles {
+            snapshots: Arc::default(),
+            last_write: Instant::now(),
+        }
+    }
+}
+
+impl SwapFiles {
+    /// Dumps every modified buffer before the default hook reports the panic
+    pub fn install_panic_hook(&self) {
+        let snapshots = self.snapshots.clone();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            // A panic while the snapshots were being updated leaves them locked
+            let snapshots = match snapshots.try_lock() {
+                Ok(snapshots) => snapshots,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) => return default_hook(info),
+            };
+            for snapshot in snapshots.values() {
+                match snapshot.write() {
+                    Ok(()) => eprintln!("Unsaved changes written to {}", snapshot.path.display()),
+                    Err(err) => eprintln!("Couldn't write {}: {}", snapshot.path.display(), err),
+                }
+            }
+            default_hook(info);
+        }));
+    }
+
+    /// Catches up with the edits since last time. Buffers that were saved
+    /// don't need their swap files anymore.
+    pub fn track(&mut self, buffers: &SecondaryMap<BufferKey, Buffer>) {
+        let mut snapshots = self.snapshots.lock().expect("swap snapshots");
+        snapshots.retain(|key, snapshot| {
+            let modified = buffers.get(*key).map_or(false, Buffer::is_modified);
+            if !modified && snapshot.written {
+                // Already gone is fine
+                let _ = std::fs::remove_file(&snapshot.path);
+            }
+            modified
+        });
+        for (key, buffer) in buffers.iter().filter(|(_, buffer)| buffer.is_modified()) {
+            if let Some(snapshot) = snapshots.get(key) {
+                if snapshot.version == buffer.version() {
+                    continue;
+                }
+            }
+            let path = match buffer_swap_path(key, buffer) {
+                Some(path) => path,
+                None => continue,
+            };
+            snapshots.insert(
+                key,
+                Snapshot {
+                    path,
+                    rope: buffer.rope().clone(),
+                    version: buffer.version(),
+                    written: false,
+                },
+            );
+        }
+    }
+
+    /// When the next write is due, if there is anything to write
+    pub fn deadline(&self) -> Option<Instant> {
+        let snapshots = self.snapshots.lock().expect("swap snapshots");
+        if snapshots.values().all(|snapshot| snapshot.written) {
+            return None;
+        }
+        Some(self.last_write + SWAP_INTERVAL)
+    }
+
+    /// Writes the snapshots that changed since the last write
+    pub fn write(&mut self) -> Result<()> {
+        self.last_write = Instant::now();
+        let mut snapshots = self.snapshots.lock().expect("swap snapshots");
+        for snapshot in snapshots.values_mut().filter(|snapshot| !snapshot.written) {
+            snapshot.write()?;
+            snapshot.written = true;
+        }
+        Ok(())
+    }
+
+    /// On a clean exit, unsaved changes were thrown away on purpose
+    pub fn clear(&mut self) {
+        let mut snapshots = self.snapshots.lock().expect("swap snapshots");
+        for (_, snapshot) in snapshots.drain().filter(|(_, snapshot)| snapshot.written) {
+            let _ = std::fs::remove_file(&snapshot.path);
+        }
+    }
+}
@@ -26,6 +26,13 @@ impl TextBuffer {
                 }
                 true
             }
+            Cmd::InsertText(text) => {
+                // Only ever a single line
+                let text = text.replace(|c| c == '\r' || c == '\n', " ");
+                self.buffer.insert_str(self.position, &text);
+                self.position += text.len();
+                true
+            }
             Cmd::MoveCursor(Direction::Left) => {
                 if self.position > 0 {
                     self.position -= 1;
@@ -58,4 +65,9 @@ impl TextBuffer {
         ui.set_cursor_pos([10., 0.]);
         ui.text(im_string);
     }
+    pub fn render_with_prompt(&self, ui: &imgui::Ui, prompt: &str) {
+        let im_string = imgui::ImString::new(format!("{}> {}", prompt, self.buffer.as_str()));
+        ui.set_cursor_pos([10., 0.]);
+        ui.text(im_string);
+    }
 }
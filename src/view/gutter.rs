@@ -0,0 +1,141 @@
+use crate::{buffer::Buffer, settings::LineNumbers};
+
+use std::{collections::HashMap, ops::Range};
+
+const SIGN_WIDTH_PX: f32 = 20.;
+const CURRENT_LINE_COLOR: [f32; 4] = [1., 0.85, 0.3, 1.];
+const MARK_COLOR: [f32; 4] = [0.55, 0.75, 1., 1.];
+
+fn log10(num: usize) -> usize {
+    match num {
+        n if n < 1 => panic!("log10 doesn't work for n < 1"),
+        n if n < 10 => 1,
+        n if n < 100 => 2,
+        n if n < 1000 => 3,
+        n if n < 10000 => 4,
+        n if n < 100_000 => 5,
+        n if n < 1_000_000 => 6,
+        _ => unimplemented!(), // Cross that bridge when we get there
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sign {
+    pub line: usize,
+    /// Only the first couple of characters fit in the column
+    pub text: String,
+    pub color: [f32; 4],
+    /// When several providers want the same line the highest priority wins
+    pub priority: i32,
+}
+
+/// Anything that wants to put markers next to lines (diagnostics, git, marks, folds...)
+pub trait SignProvider {
+    fn signs(&self, buffer: &Buffer, lines: Range<usize>) -> Vec<Sign>;
+}
+
+/// Shows `m{a-zA-Z}` marks by name, the first one when a line has several
+pub struct MarkSigns;
+
+impl SignProvider for MarkSigns {
+    fn signs(&self, buffer: &Buffer, lines: Range<usize>) -> Vec<Sign> {
+        buffer
+            .marks()
+            .into_iter()
+            .filter(|(_, point)| lines.contains(&(point.y as usize)))
+            .map(|(name, point)| Sign {
+                line: point.y as usize,
+                text: name.to_string(),
+                color: MARK_COLOR,
+                priority: 0,
+            })
+            .collect()
+    }
+}
+
+/// The column to the left of the text with signs and line numbers
+#[derive(Default)]
+pub struct Gutter {
+    providers: Vec<Box<dyn SignProvider>>,
+}
+
+impl Gutter {
+    pub fn register(&mut self, provider: Box<dyn SignProvider>) {
+        self.providers.push(provider);
+    }
+
+    fn collect_signs(&self, buffer: &Buffer) -> HashMap<usize, Sign> {
+        let mut signs: HashMap<usize, Sign> = HashMap::new();
+        for sign in self
+            .providers
+            .iter()
+            .flat_map(|provider| provider.signs(buffer, 0..buffer.len_lines()))
+        {
+            match signs.get(&sign.line) {
+                Some(existing) if existing.priority >= sign.priority => {}
+                _ => {
+                    signs.insert(sign.line, sign);
+                }
+            }
+        }
+        signs
+    }
+
+    /// Renders the gutter and returns how much horizontal space it took
+    pub fn render(
+        &self,
+        ui: &imgui::Ui,
+        buffer: &Buffer,
+        cursor_row: usize,
+        line_numbers: LineNumbers,
+    ) -> f32 {
+        let line_len = buffer.len_lines();
+        let signs = self.collect_signs(buffer);
+        // Only takes up space while there is something to show, like vim's `signcolumn=auto`
+        let sign_width = if signs.is_empty() { 0. } else { SIGN_WIDTH_PX };
+        let number_width = match line_numbers {
+            LineNumbers::Off => 0.,
+            _ => log10(line_len) as f32 * 10.,
+        };
+
+        if sign_width > 0. {
+            ui.group(|| {
+                ui.set_cursor_pos([0., 0.]);
+                ui.new_line();
+                ui.indent_by(5.);
+                for line in 0..line_len {
+                    match signs.get(&line) {
+                        Some(sign) => {
+                            let text: String = sign.text.chars().take(2).collect();
+                            ui.text_colored(sign.color, &text);
+                        }
+                        None => ui.new_line(),
+                    }
+                }
+            });
+        }
+        if line_numbers != LineNumbers::Off {
+            ui.group(|| {
+                ui.set_cursor_pos([0., 0.]);
+                ui.new_line();
+                ui.indent_by(5. + sign_width);
+                for line in 0..line_len {
+                    let number = match line_numbers {
+                        LineNumbers::Relative | LineNumbers::Hybrid if line != cursor_row => {
+                            (line as isize - cursor_row as isize).abs() as usize
+                        }
+                        LineNumbers::Relative => 0,
+                        _ => line + 1,
+                    };
+                    let text = format!("{}", number);
+                    if line == cursor_row {
+                        ui.text_colored(CURRENT_LINE_COLOR, &text);
+                    } else {
+                        ui.text(&text);
+                    }
+                }
+            });
+        }
+        5. + sign_width + number_width
+    }
+}
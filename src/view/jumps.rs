@@ -0,0 +1,52 @@
+use crate::{buffer::BufferKey, point::Point};
+
+/// Oldest jumps get dropped past this, same as vim
+const MAX_JUMPS: usize = 100;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Jump {
+    pub buffer: BufferKey,
+    pub position: Point,
+}
+
+/// Where big motions left from, walked with Ctrl-O and Ctrl-I
+#[derive(Debug, Clone, Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    /// `jumps.len()` when not walking the list
+    index: usize,
+}
+
+impl JumpList {
+    /// Only one jump per line is kept, the newest
+    pub fn push(&mut self, jump: Jump) {
+        self.jumps
+            .retain(|old| old.buffer != jump.buffer || old.position.y != jump.position.y);
+        self.jumps.push(jump);
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+        self.index = self.jumps.len();
+    }
+
+    /// `current` is remembered first so Ctrl-I can come back to it
+    pub fn older(&mut self, current: Jump, count: usize) -> Option<Jump> {
+        if self.index == self.jumps.len() {
+            self.push(current);
+            self.index = self.jumps.len() - 1;
+        }
+        if self.index == 0 {
+            return None;
+        }
+        self.index = self.index.saturating_sub(count);
+        self.jumps.get(self.index).copied()
+    }
+
+    pub fn newer(&mut self, count: usize) -> Option<Jump> {
+        if self.index + 1 >= self.jumps.len() {
+            return None;
+        }
+        self.index = (self.index + count).min(self.jumps.len() - 1);
+        self.jumps.get(self.index).copied()
+    }
+}
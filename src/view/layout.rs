@@ -0,0 +1,247 @@
+use super::ViewKey;
+use crate::msg::{Direction, SplitDirection};
+
+const MIN_RATIO: f32 = 0.1;
+const MAX_RATIO: f32 = 0.9;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn unit() -> Rect {
+        Rect {
+            x: 0.,
+            y: 0.,
+            width: 1.,
+            height: 1.,
+        }
+    }
+
+    fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    fn split(&self, direction: SplitDirection, ratio: f32) -> (Rect, Rect) {
+        match direction {
+            SplitDirection::Horizontal => {
+                let first_height = self.height * ratio;
+                (
+                    Rect {
+                        height: first_height,
+                        ..*self
+                    },
+                    Rect {
+                        y: self.y + first_height,
+                        height: self.height - first_height,
+                        ..*self
+                    },
+                )
+            }
+            SplitDirection::Vertical => {
+                let first_width = self.width * ratio;
+                (
+                    Rect {
+                        width: first_width,
+                        ..*self
+                    },
+                    Rect {
+                        x: self.x + first_width,
+                        width: self.width - first_width,
+                        ..*self
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// Binary tree of splits, the leaves being the views that get rendered.
+/// Horizontal splits stack their children top to bottom, vertical ones left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layout {
+    View(ViewKey),
+    Split {
+        direction: SplitDirection,
+        ratio: f32,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Layout {
+    fn is_view(&self, target: ViewKey) -> bool {
+        match self {
+            Layout::View(key) => *key == target,
+            Layout::Split { .. } => false,
+        }
+    }
+
+    pub fn contains(&self, target: ViewKey) -> bool {
+        match self {
+            Layout::View(key) => *key == target,
+            Layout::Split { first, second, .. } => {
+                first.contains(target) || second.contains(target)
+            }
+        }
+    }
+
+    /// Puts `new_view` next to `target`, taking the top (or left) half of its space
+    pub fn split(&mut self, target: ViewKey, new_view: ViewKey, direction: SplitDirection) -> bool {
+        match self {
+            Layout::View(key) if *key == target => {
+                *self = Layout::Split {
+                    direction,
+                    ratio: 0.5,
+                    first: Box::new(Layout::View(new_view)),
+                    second: Box::new(Layout::View(target)),
+                };
+                true
+            }
+            Layout::View(_) => false,
+            Layout::Split { first, second, .. } => {
+                first.split(target, new_view, direction)
+                    || second.split(target, new_view, direction)
+            }
+        }
+    }
+
+    /// Removes `target` and gives its space to its sibling
+    pub fn remove(&mut self, target: ViewKey) -> bool {
+        match self {
+            Layout::View(_) => false,
+            Layout::Split { first, second, .. } => {
+                let remaining = if first.is_view(target) {
+                    Some(std::mem::replace(second.as_mut(), Layout::View(target)))
+                } else if second.is_view(target) {
+                    Some(std::mem::replace(first.as_mut(), Layout::View(target)))
+                } else {
+                    None
+                };
+                match remaining {
+                    Some(remaining) => {
+                        *self = remaining;
+                        true
+                    }
+                    None => first.remove(target) || second.remove(target),
+                }
+            }
+        }
+    }
+
+    /// Grows (or shrinks with a negative delta) the closest split around `target`
+    /// that runs in `direction`
+    pub fn resize(&mut self, target: ViewKey, direction: SplitDirection, delta: f32) -> bool {
+        match self {
+            Layout::View(_) => false,
+            Layout::Split {
+                direction: split_direction,
+                ratio,
+                first,
+                second,
+            } => {
+                if first.resize(target, direction, delta) || second.resize(target, direction, delta)
+                {
+                    return true;
+                }
+                if *split_direction != direction {
+                    return false;
+                }
+                if first.contains(target) {
+                    *ratio += delta;
+                } else if second.contains(target) {
+                    *ratio -= delta;
+                } else {
+                    return false;
+                }
+                *ratio = ratio.max(MIN_RATIO).min(MAX_RATIO);
+                true
+            }
+        }
+    }
+
+    pub fn views(&self) -> Vec<ViewKey> {
+        let mut views = Vec::new();
+        self.collect_views(&mut views);
+        views
+    }
+
+    fn collect_views(&self, views: &mut Vec<ViewKey>) {
+        match self {
+            Layout::View(key) => views.push(*key),
+            Layout::Split { first, second, .. } => {
+                first.collect_views(views);
+                second.collect_views(views);
+            }
+        }
+    }
+
+    pub fn rects(&self, area: Rect) -> Vec<(ViewKey, Rect)> {
+        let mut rects = Vec::new();
+        self.collect_rects(area, &mut rects);
+        rects
+    }
+
+    fn collect_rects(&self, area: Rect, rects: &mut Vec<(ViewKey, Rect)>) {
+        match self {
+            Layout::View(key) => rects.push((*key, area)),
+            Layout::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                let (first_area, second_area) = area.split(*direction, *ratio);
+                first.collect_rects(first_area, rects);
+                second.collect_rects(second_area, rects);
+            }
+        }
+    }
+
+    /// Finds the closest view on the `direction` side of `current`
+    pub fn neighbor(&self, current: ViewKey, direction: Direction) -> Option<ViewKey> {
+        const EPSILON: f32 = 0.0001;
+        let rects = self.rects(Rect::unit());
+        let (_, from) = rects.iter().find(|(key, _)| *key == current)?;
+        rects
+            .iter()
+            .filter(|(key, _)| *key != current)
+            .filter_map(|(key, rect)| {
+                let overlaps_vertically = rect.y < from.bottom() && from.y < rect.bottom();
+                let overlaps_horizontally = rect.x < from.right() && from.x < rect.right();
+                let distance = match direction {
+                    Direction::Left if overlaps_vertically => from.x - rect.right(),
+                    Direction::Right if overlaps_vertically => rect.x - from.right(),
+                    Direction::Up if overlaps_horizontally => from.y - rect.bottom(),
+                    Direction::Down if overlaps_horizontally => rect.y - from.bottom(),
+                    _ => return None,
+                };
+                if distance < -EPSILON {
+                    None
+                } else {
+                    // Prefer the one lined up with the top left corner of the current view
+                    let offset = match direction {
+                        Direction::Left | Direction::Right => (rect.y - from.y).abs(),
+                        Direction::Up | Direction::Down => (rect.x - from.x).abs(),
+                    };
+                    Some((*key, distance, offset))
+                }
+            })
+            .fold(
+                None,
+                |best: Option<(ViewKey, f32, f32)>, candidate| match best {
+                    Some(best) if (best.1, best.2) <= (candidate.1, candidate.2) => Some(best),
+                    _ => Some(candidate),
+                },
+            )
+            .map(|(key, _, _)| key)
+    }
+}
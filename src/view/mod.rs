@@ -0,0 +1,167 @@
+use crate::{
+    buffer::{get_visible_lines, Buffer, BufferKey},
+    cursor::Cursor,
+    msg::MouseAction,
+    point::Point,
+    settings::Settings,
+};
+
+use slotmap::DefaultKey;
+
+mod gutter;
+mod jumps;
+mod layout;
+mod mouse;
+mod tab;
+pub use gutter::{Gutter, MarkSigns, Sign, SignProvider};
+pub use jumps::Jump;
+use jumps::JumpList;
+pub use layout::{Layout, Rect};
+use mouse::MouseTracker;
+pub use tab::Tab;
+
+pub type ViewKey = DefaultKey;
+
+/// A window onto a buffer. Several views can show the same buffer,
+/// each with its own cursor and scroll position.
+#[derive(Clone)]
+pub struct View {
+    pub buffer: BufferKey,
+    pub cursor: Cursor,
+    pub scroll_y: f32,
+    pub line_count: usize,
+    /// First line that was visible last frame
+    top_line: usize,
+    /// Set by the scroll wheel, applied on the next render
+    scroll_to: Option<usize>,
+    mouse: MouseTracker,
+    jumps: JumpList,
+}
+
+impl View {
+    pub fn new(buffer: BufferKey) -> View {
+        View {
+            buffer,
+            cursor: Cursor::new(),
+            scroll_y: 0.,
+            line_count: 0,
+            top_line: 0,
+            scroll_to: None,
+            mouse: MouseTracker::default(),
+            jumps: JumpList::default(),
+        }
+    }
+
+    pub fn set_buffer(&mut self, buffer: BufferKey) {
+        self.buffer = buffer;
+        self.cursor = Cursor::new();
+    }
+
+    fn current_jump(&self, buffer: &Buffer) -> Jump {
+        Jump {
+            buffer: self.buffer,
+            position: self.cursor.clamped(&buffer.slice()),
+        }
+    }
+
+    /// Remembers where the cursor is before a big motion, `buffer` is the one being shown
+    pub fn push_jump(&mut self, buffer: &Buffer) {
+        let jump = self.current_jump(buffer);
+        self.jumps.push(jump);
+    }
+
+    /// Ctrl-O
+    pub fn older_jump(&mut self, buffer: &Buffer, count: usize) -> Option<Jump> {
+        let current = self.current_jump(buffer);
+        self.jumps.older(current, count)
+    }
+
+    /// Ctrl-I
+    pub fn newer_jump(&mut self, count: usize) -> Option<Jump> {
+        self.jumps.newer(count)
+    }
+
+    /// Returns whatever the mouse did to this view during the frame
+    pub fn render(
+        &mut self,
+        ui: &imgui::Ui,
+        buffer: &Buffer,
+        focused: bool,
+        gutter: &Gutter,
+        settings: &Settings,
+    ) -> Option<MouseAction> {
+        let line_height = ui.text_line_height_with_spacing();
+        self.line_count = get_visible_lines(ui);
+        let line_offset_px = gutter.render(ui, buffer, self.cursor.row(), settings.line_numbers());
+        buffer.render(ui, &self.cursor, line_offset_px, focused, settings.rainbow);
+        // After the cursor so it wins over the cursor pulling the view back
+        if let Some(top_line) = self.scroll_to.take() {
+            ui.set_scroll_y(top_line as f32 * line_height);
+        }
+        self.scroll_y = ui.scroll_y();
+        self.top_line = (self.scroll_y / line_height) as usize;
+        self.mouse
+            .update(ui, &buffer.slice(), line_offset_px, settings.mouse_scroll)
+    }
+
+    pub fn handle_mouse(&mut self, action: MouseAction, buffer: &Buffer) {
+        let rope = buffer.slice();
+        match action {
+            MouseAction::Click(point) => self.cursor.set_position(point, &rope),
+            MouseAction::Drag(point) => self.cursor.extend_selection(point, &rope),
+            MouseAction::SelectWord(point) => {
+                let is_word = |c: char| c.is_alphanumeric() || c == '_';
+                let index = point.index(&rope);
+                if index >= rope.len_chars() {
+                    self.cursor.set_position(point, &rope);
+                    return;
+                }
+                let (mut start, mut end) = (index, index);
+                if is_word(rope.char(index)) {
+                    while start > 0 && is_word(rope.char(start - 1)) {
+                        start -= 1;
+                    }
+                    while end + 1 < rope.len_chars() && is_word(rope.char(end + 1)) {
+                        end += 1;
+                    }
+                }
+                self.cursor.select(
+                    Point::from_index(start, &rope),
+                    Point::from_index(end, &rope),
+                    &rope,
+                );
+            }
+            MouseAction::SelectLines(first, last) => {
+                let line_end = |line: usize| Point {
+                    x: rope.line(line).len_chars().saturating_sub(1) as u16,
+                    y: line as u16,
+                };
+                let line_start = |line: usize| Point {
+                    x: 0,
+                    y: line as u16,
+                };
+                // The cursor goes on the end that is being dragged
+                if first <= last {
+                    self.cursor.select(line_start(first), line_end(last), &rope);
+                } else {
+                    self.cursor.select(line_end(first), line_start(last), &rope);
+                }
+            }
+            MouseAction::Scroll(lines) => {
+                let max_top = rope.len_lines().saturating_sub(1) as isize;
+                let top_line = (self.top_line as isize + lines).max(0).min(max_top) as usize;
+                self.scroll_to = Some(top_line);
+                // Keep the cursor on screen, otherwise it pulls the view straight back
+                let last_line = top_line + self.line_count.saturating_sub(1);
+                let row = self.cursor.row().max(top_line).min(last_line);
+                if row != self.cursor.row() {
+                    let point = Point {
+                        x: self.cursor.col() as u16,
+                        y: row as u16,
+                    };
+                    self.cursor.set_position(point, &rope);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{cursor::point_at, msg::MouseAction, point::Point};
+
+use imgui::MouseButton;
+use ropey::RopeSlice;
+
+use std::time::{Duration, Instant};
+
+/// Presses on the same spot closer together than this count as one double/triple click
+const MULTI_CLICK: Duration = Duration::from_millis(400);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum DragKind {
+    Text,
+    /// Started on the line numbers, so whole lines get selected
+    Lines(usize),
+}
+
+/// Turns imgui's per-frame mouse state into actions for one view
+#[derive(Debug, Clone, Default)]
+pub struct MouseTracker {
+    last_press: Option<(Instant, Point)>,
+    clicks: u8,
+    /// Only set while the button is held after pressing inside this view
+    drag: Option<DragKind>,
+    last_point: Option<Point>,
+}
+
+impl MouseTracker {
+    /// Has to run inside the view's window. `gutter_width` is the space left of the text.
+    pub fn update(
+        &mut self,
+        ui: &imgui::Ui,
+        rope: &RopeSlice,
+        gutter_width: f32,
+        scroll_lines: u64,
+    ) -> Option<MouseAction> {
+        let [mouse_x, mouse_y] = ui.io().mouse_pos;
+        let [window_x, window_y] = ui.window_pos();
+        let local = [mouse_x - window_x, mouse_y - window_y];
+        let hovered = ui.is_window_hovered();
+
+        let wheel = ui.io().mouse_wheel;
+        if hovered && wheel != 0. && scroll_lines > 0 {
+            // Wheel up is positive, which moves the text down
+            return Some(MouseAction::Scroll(-(wheel * scroll_lines as f32) as isize));
+        }
+
+        if hovered && ui.is_mouse_clicked(MouseButton::Left) {
+            let point = point_at(ui, rope, gutter_width, local);
+            let now = Instant::now();
+            self.clicks = match self.last_press {
+                Some((time, last)) if last == point && now - time < MULTI_CLICK => {
+                    self.clicks % 3 + 1
+                }
+                _ => 1,
+            };
+            self.last_press = Some((now, point));
+            self.last_point = Some(point);
+            let line = point.y as usize;
+            if local[0] < gutter_width {
+                self.drag = Some(DragKind::Lines(line));
+                return Some(MouseAction::SelectLines(line, line));
+            }
+            self.drag = Some(DragKind::Text);
+            return Some(match self.clicks {
+                1 => MouseAction::Click(point),
+                2 => MouseAction::SelectWord(point),
+                _ => MouseAction::SelectLines(line, line),
+            });
+        }
+
+        if !ui.is_mouse_down(MouseButton::Left) {
+            self.drag = None;
+            return None;
+        }
+        let drag = self.drag?;
+        let point = point_at(ui, rope, gutter_width, local);
+        if self.last_point == Some(point) {
+            return None;
+        }
+        self.last_point = Some(point);
+        Some(match drag {
+            DragKind::Text => MouseAction::Drag(point),
+            DragKind::Lines(start) => MouseAction::SelectLines(start, point.y as usize),
+        })
+    }
+}
@@ -0,0 +1,95 @@
+use super::{Layout, View, ViewKey};
+use crate::{
+    buffer::{Buffer, BufferKey},
+    msg::{Direction, SplitDirection},
+};
+
+use slotmap::{SecondaryMap, SlotMap};
+
+/// A tab page holds its own split layout over the shared views
+pub struct Tab {
+    pub layout: Layout,
+    pub current_view: ViewKey,
+}
+
+impl Tab {
+    pub fn new(view: ViewKey) -> Tab {
+        Tab {
+            layout: Layout::View(view),
+            current_view: view,
+        }
+    }
+
+    pub fn view_count(&self) -> usize {
+        self.layout.views().len()
+    }
+
+    pub fn split(&mut self, new_view: ViewKey, direction: SplitDirection) {
+        self.layout.split(self.current_view, new_view, direction);
+        self.current_view = new_view;
+    }
+
+    pub fn focus(&mut self, direction: Direction) -> bool {
+        match self.layout.neighbor(self.current_view, direction) {
+            Some(view) => {
+                self.current_view = view;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cycle(&mut self) {
+        let views = self.layout.views();
+        let position = views
+            .iter()
+            .position(|key| *key == self.current_view)
+            .unwrap_or(0);
+        self.current_view = views[(position + 1) % views.len()];
+    }
+
+    /// Takes the current view out of the layout and focuses the one that took its place.
+    /// Callers need to make sure it isn't the only view.
+    pub fn close_current(&mut self) -> ViewKey {
+        let closed = self.current_view;
+        let position = self
+            .layout
+            .views()
+            .iter()
+            .position(|key| *key == closed)
+            .unwrap_or(0);
+        self.layout.remove(closed);
+        let remaining = self.layout.views();
+        self.current_view = remaining[position.min(remaining.len() - 1)];
+        closed
+    }
+
+    /// Every view but the current one, which are no longer in the layout
+    pub fn only(&mut self) -> Vec<ViewKey> {
+        let closed = self
+            .layout
+            .views()
+            .into_iter()
+            .filter(|key| *key != self.current_view)
+            .collect();
+        self.layout = Layout::View(self.current_view);
+        closed
+    }
+
+    pub fn label(
+        &self,
+        views: &SlotMap<ViewKey, View>,
+        buffers: &SecondaryMap<BufferKey, Buffer>,
+    ) -> String {
+        let buffer = &buffers[views[self.current_view].buffer];
+        let view_count = self.view_count();
+        let mut label = buffer.name();
+        if view_count > 1 {
+            label = format!("{} {}", view_count, label);
+        }
+        if buffer.is_modified() {
+            label.push_str(" +");
+        }
+        label
+    }
+}